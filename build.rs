@@ -1,3 +1,198 @@
+// Shared with the library so the generator deserializes exactly what
+// `DeviceDetector::from_dir` would.
+#[allow(dead_code)]
+#[path = "src/db.rs"]
+mod db;
+
+#[allow(dead_code)]
+#[path = "src/dataset_version.rs"]
+mod dataset_version;
+
 fn main() {
     fixtures::build::watch_dir("vendor/device-detector/Tests/fixtures");
+
+    if std::env::var_os("CARGO_FEATURE_PRECOMPILED").is_some() {
+        precompiled::generate();
+    }
+}
+
+/// Code generation for the `precompiled` feature: read the Matomo YAML once
+/// here and emit static tables consumed by `src/precompiled.rs`.
+mod precompiled {
+    use super::{dataset_version, db};
+    use std::fmt::Write as _;
+    use std::path::{Path, PathBuf};
+
+    const DEFAULT_DIR: &str = "vendor/device-detector/regexes";
+
+    fn load<T: serde::de::DeserializeOwned>(path: &Path) -> T {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let content = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("precompiled: cannot read {}: {}", path.display(), e));
+        serde_yaml::from_str(&content)
+            .unwrap_or_else(|e| panic!("precompiled: invalid YAML in {}: {}", path.display(), e))
+    }
+
+    fn pairs(items: impl IntoIterator<Item = (String, String)>) -> String {
+        let rows: Vec<String> = items
+            .into_iter()
+            .map(|(k, v)| format!("({:?}, {:?})", k, v))
+            .collect();
+        format!("&[{}]", rows.join(", "))
+    }
+
+    fn clients(out: &mut String, name: &str, path: &Path) {
+        let entries: Vec<db::BrowserEntry> = load(path);
+        writeln!(out, "pub(crate) static {}: &[ClientRow] = &[", name).unwrap();
+        for e in entries {
+            let engine = match e.engine {
+                Some(eng) => format!(
+                    "Some(({:?}, {}))",
+                    eng.default,
+                    match eng.versions {
+                        Some(v) => format!("Some({})", pairs(v)),
+                        None => "None".to_string(),
+                    }
+                ),
+                None => "None".to_string(),
+            };
+            writeln!(
+                out,
+                "    ClientRow {{ regex: {:?}, name: {:?}, version: {:?}, engine: {} }},",
+                e.regex, e.name, e.version, engine
+            )
+            .unwrap();
+        }
+        out.push_str("];\n");
+    }
+
+    pub fn generate() {
+        println!("cargo:rerun-if-env-changed=DEVICE_DETECTOR_REGEXES");
+        let dir = PathBuf::from(
+            std::env::var("DEVICE_DETECTOR_REGEXES").unwrap_or_else(|_| DEFAULT_DIR.to_string()),
+        );
+        if !dir.is_dir() {
+            panic!(
+                "precompiled: regexes directory {} not found; clone Matomo's device-detector \
+                 into vendor/ or set DEVICE_DETECTOR_REGEXES",
+                dir.display()
+            );
+        }
+        let client_dir = dir.join("client");
+        let mut out = String::new();
+
+        let version = dataset_version::read_dataset_version(&dir);
+        writeln!(
+            out,
+            "pub(crate) static DATASET_VERSION: Option<&str> = {:?};",
+            version
+        )
+        .unwrap();
+
+        let bots: Vec<db::BotEntry> = load(&dir.join("bots.yml"));
+        out.push_str("pub(crate) static BOTS: &[BotRow] = &[\n");
+        for b in bots {
+            let producer = b.producer.map(|p| (p.name, p.url));
+            writeln!(
+                out,
+                "    BotRow {{ regex: {:?}, name: {:?}, category: {:?}, url: {:?}, producer: {:?} }},",
+                b.regex, b.name, b.category, b.url, producer
+            )
+            .unwrap();
+        }
+        out.push_str("];\n");
+
+        let oss: Vec<db::OsEntry> = load(&dir.join("oss.yml"));
+        out.push_str("pub(crate) static OSS: &[OsRow] = &[\n");
+        for o in oss {
+            writeln!(
+                out,
+                "    OsRow {{ regex: {:?}, name: {:?}, version: {:?} }},",
+                o.regex, o.name, o.version
+            )
+            .unwrap();
+        }
+        out.push_str("];\n");
+
+        for (name, file) in [
+            ("BROWSERS", "browsers.yml"),
+            ("FEED_READERS", "feed_readers.yml"),
+            ("MOBILE_APPS", "mobile_apps.yml"),
+            ("LIBRARIES", "libraries.yml"),
+            ("MEDIA_PLAYERS", "mediaplayers.yml"),
+            ("PIMS", "pim.yml"),
+        ] {
+            clients(&mut out, name, &client_dir.join(file));
+        }
+
+        let engines: Vec<db::EngineEntry> = load(&client_dir.join("browser_engine.yml"));
+        writeln!(
+            out,
+            "pub(crate) static ENGINES: &[(&str, &str)] = {};",
+            pairs(engines.into_iter().map(|e| (e.regex, e.name)))
+        )
+        .unwrap();
+
+        let fragments: db::VendorFragmentMap = load(&dir.join("vendorfragments.yml"));
+        out.push_str("pub(crate) static VENDOR_FRAGMENTS: &[(&str, &[&str])] = &[\n");
+        for (brand, patterns) in fragments {
+            writeln!(out, "    ({:?}, &{:?}),", brand, patterns).unwrap();
+        }
+        out.push_str("];\n");
+
+        for (name, file) in [("APP_HINTS", "apps.yml"), ("BROWSER_HINTS", "browsers.yml")] {
+            let hints: db::HintMap = load(&client_dir.join("hints").join(file));
+            let mut hints: Vec<_> = hints.into_iter().collect();
+            hints.sort();
+            writeln!(
+                out,
+                "pub(crate) static {}: &[(&str, &str)] = {};",
+                name,
+                pairs(hints)
+            )
+            .unwrap();
+        }
+
+        // Every device file present; the detector picks the ones it knows.
+        let device_dir = dir.join("device");
+        let mut device_files: Vec<_> = std::fs::read_dir(&device_dir)
+            .map(|entries| entries.filter_map(|e| e.ok().map(|e| e.path())).collect())
+            .unwrap_or_default();
+        device_files.retain(|p: &PathBuf| p.extension().is_some_and(|ext| ext == "yml"));
+        device_files.sort();
+        out.push_str("pub(crate) static DEVICES: &[(&str, &[DeviceBrandRow])] = &[\n");
+        for path in device_files {
+            let brands: db::DeviceBrandMap = load(&path);
+            let file = path.file_name().unwrap().to_string_lossy().into_owned();
+            writeln!(out, "    ({:?}, &[", file).unwrap();
+            for (brand, e) in brands {
+                let models = match e.models {
+                    Some(models) => {
+                        let rows: Vec<String> = models
+                            .into_iter()
+                            .map(|m| {
+                                format!(
+                                    "ModelRow {{ regex: {:?}, model: {:?}, device: {:?}, brand: {:?} }}",
+                                    m.regex, m.model, m.device, m.brand
+                                )
+                            })
+                            .collect();
+                        format!("Some(&[{}])", rows.join(", "))
+                    }
+                    None => "None".to_string(),
+                };
+                writeln!(
+                    out,
+                    "        DeviceBrandRow {{ brand: {:?}, regex: {:?}, device: {:?}, model: {:?}, models: {} }},",
+                    brand, e.regex, e.device, e.model, models
+                )
+                .unwrap();
+            }
+            out.push_str("    ]),\n");
+        }
+        out.push_str("];\n");
+
+        let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+        std::fs::write(out_dir.join("precompiled.rs"), out).unwrap();
+    }
 }
@@ -0,0 +1,65 @@
+//! Rough parse-throughput benchmark.
+//!
+//! ```bash
+//! cargo run --release --example bench -- vendor/device-detector/regexes user_agents.txt
+//! ```
+//!
+//! `user_agents.txt` holds one UA per line.  Runs every UA through a default
+//! detector and one built with `regex_set_gate(true)`, checks that both agree,
+//! and prints the time taken by each, plus the time for
+//! `parse_without_device` on the default detector.
+
+use device_detector_rs::{Detection, DeviceDetector};
+use std::time::Instant;
+
+fn summary(d: &Detection) -> String {
+    format!(
+        "{:?}|{:?}|{:?}|{:?}",
+        d.bot().map(|b| &b.name),
+        d.os().map(|o| (&o.name, &o.version)),
+        d.client().map(|c| (&c.name, &c.version)),
+        d.device().map(|d| (d.kind, &d.brand, &d.model)),
+    )
+}
+
+fn main() -> device_detector_rs::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let regexes = args
+        .next()
+        .unwrap_or_else(|| "vendor/device-detector/regexes".into());
+    let ua_file = args.next().expect("usage: bench <regexes dir> <ua file>");
+    let content = std::fs::read_to_string(ua_file)?;
+    let uas: Vec<&str> = content.lines().filter(|l| !l.is_empty()).collect();
+
+    let plain = DeviceDetector::from_dir(&regexes)?;
+    let gated = DeviceDetector::builder(&regexes)
+        .regex_set_gate(true)
+        .build()?;
+
+    for (name, dd) in [("default", &plain), ("regex_set_gate", &gated)] {
+        let t = Instant::now();
+        for ua in &uas {
+            std::hint::black_box(dd.parse(ua));
+        }
+        println!("{:<16} {} UAs in {:?}", name, uas.len(), t.elapsed());
+    }
+
+    let t = Instant::now();
+    for ua in &uas {
+        std::hint::black_box(plain.parse_without_device(ua, None));
+    }
+    println!(
+        "{:<16} {} UAs in {:?}",
+        "without_device",
+        uas.len(),
+        t.elapsed()
+    );
+
+    let mismatches = uas
+        .iter()
+        .filter(|ua| summary(&plain.parse(ua)) != summary(&gated.parse(ua)))
+        .count();
+    println!("mismatches: {}", mismatches);
+
+    Ok(())
+}
@@ -0,0 +1,11 @@
+/// Userscript and extension runtimes that announce themselves in the UA.
+///
+/// Matomo's dataset has no entries for these, so they are kept here as
+/// `(regex, name)` pairs and compiled into an ordinary client parser.  The
+/// version comes from capture group 1 when present.
+pub(crate) const BROWSER_EXTENSIONS: &[(&str, &str)] = &[
+    (r"Tampermonkey(?:/(\d+[\.\d]+))?", "Tampermonkey"),
+    (r"Greasemonkey(?:/(\d+[\.\d]+))?", "Greasemonkey"),
+    (r"Violentmonkey(?:/(\d+[\.\d]+))?", "Violentmonkey"),
+    (r"Userscripts(?:/(\d+[\.\d]+))?", "Userscripts"),
+];
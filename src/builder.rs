@@ -0,0 +1,258 @@
+use super::device_detector::DeviceDetector;
+use super::error::Result;
+use super::parser::DEFAULT_LITERAL_MIN_LEN;
+use super::stage::{DetectionStage, Stage};
+use super::types::{DetectionOwned, DeviceFileSpec, DeviceSelection};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Callback registered with [`DeviceDetectorBuilder::with_post_hook`].
+pub type PostHookFn = dyn Fn(&str, &mut DetectionOwned) + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct PostHook(pub(crate) Arc<PostHookFn>);
+
+impl fmt::Debug for PostHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PostHook")
+    }
+}
+
+/// Callback registered with [`DeviceDetectorBuilder::with_version_formatter`].
+pub type VersionFormatterFn = dyn Fn(&str) -> String + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct VersionFormatter(pub(crate) Arc<VersionFormatterFn>);
+
+impl fmt::Debug for VersionFormatter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("VersionFormatter")
+    }
+}
+
+/// Configures and builds a [`DeviceDetector`].
+///
+/// Obtained from [`DeviceDetector::builder`]; [`DeviceDetector::from_dir`] is
+/// shorthand for building with the defaults.
+#[derive(Debug, Clone)]
+pub struct DeviceDetectorBuilder {
+    pub(crate) dir: PathBuf,
+    pub(crate) strict: bool,
+    pub(crate) bots_dont_short_circuit: bool,
+    pub(crate) regex_set_gate: bool,
+    pub(crate) standard_regex_only: bool,
+    pub(crate) literal_min_len: usize,
+    pub(crate) max_fancy_checks_per_stage: Option<usize>,
+    pub(crate) unknown_xrw_as_app: bool,
+    pub(crate) prefer_notebook_type: bool,
+    pub(crate) reset_inconsistent_brand: bool,
+    pub(crate) prefilter_implies_type: bool,
+    pub(crate) tv_client_names: Vec<String>,
+    pub(crate) device_selection: DeviceSelection,
+    pub(crate) device_files: Vec<DeviceFileSpec>,
+    pub(crate) stages: Vec<Stage>,
+    pub(crate) post_hook: Option<PostHook>,
+    pub(crate) version_formatter: Option<VersionFormatter>,
+}
+
+impl DeviceDetectorBuilder {
+    pub(crate) fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            strict: false,
+            bots_dont_short_circuit: false,
+            regex_set_gate: false,
+            standard_regex_only: false,
+            literal_min_len: DEFAULT_LITERAL_MIN_LEN,
+            max_fancy_checks_per_stage: None,
+            unknown_xrw_as_app: false,
+            prefer_notebook_type: false,
+            reset_inconsistent_brand: false,
+            prefilter_implies_type: false,
+            tv_client_names: Vec::new(),
+            device_selection: DeviceSelection::FirstMatch,
+            device_files: DeviceFileSpec::matomo_defaults(),
+            stages: Vec::new(),
+            post_hook: None,
+            version_formatter: None,
+        }
+    }
+
+    /// Fail the build when one of the device files is missing from
+    /// `regexes/device/`.  By default a missing file is recorded (see
+    /// [`DeviceDetector::missing_device_files`]) and its parser is skipped,
+    /// which keeps older Matomo checkouts usable.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Keep running OS/client/device detection after a bot match.  The bot
+    /// is still reported in [`Detection::bot`](crate::Detection::bot).  Off
+    /// by default, matching Matomo which stops at the bot.
+    pub fn bots_dont_short_circuit(mut self, enabled: bool) -> Self {
+        self.bots_dont_short_circuit = enabled;
+        self
+    }
+
+    /// Put a `RegexSet` in front of each flat-list parser (bots, OS,
+    /// clients, engines, vendor fragments) so UAs that match none of its
+    /// standard patterns skip the prefiltered pass.  Off by default; measure
+    /// with `examples/bench.rs` on your own traffic before enabling.
+    pub fn regex_set_gate(mut self, enabled: bool) -> Self {
+        self.regex_set_gate = enabled;
+        self
+    }
+
+    /// Drop every entry whose regex needs `fancy_regex` (lookaround,
+    /// backreferences) so matching runs purely on the `regex` crate.
+    ///
+    /// This trades accuracy for throughput: UAs that only a dropped entry
+    /// would have recognised fall through to a later, more generic entry or
+    /// go undetected.  See [`DeviceDetector::stats`] for how many entries
+    /// were dropped.  Model regexes inside a brand are kept.
+    pub fn standard_regex_only(mut self, enabled: bool) -> Self {
+        self.standard_regex_only = enabled;
+        self
+    }
+
+    /// Minimum literal length (in bytes, default 3) for the Aho-Corasick
+    /// prefilter in front of each standard regex set.
+    ///
+    /// A pattern whose literals are all shorter is an always-candidate,
+    /// checked against every UA.  Lowering it prefilters datasets with many
+    /// short brand tokens at the cost of a larger automaton; raising it
+    /// shrinks the automaton but leaves more patterns unfiltered.  Matching
+    /// results are the same either way.
+    pub fn literal_min_len(mut self, len: usize) -> Self {
+        self.literal_min_len = len;
+        self
+    }
+
+    /// Stop after trying `max` fancy-regex entries in each parser a UA goes
+    /// through (bots, OS, each client list, each device file), returning the
+    /// standard-regex match if there is one, or nothing.
+    ///
+    /// A coarse latency guard for hostile input, separate from fancy_regex's
+    /// per-regex backtrack limit.  Ordinary UAs reach a match within a few
+    /// fancy entries, so only pathological inputs are affected, but an entry
+    /// past the cap can no longer win and a later, more generic one (or none)
+    /// is reported instead.  Uncapped by default.
+    pub fn max_fancy_checks_per_stage(mut self, max: usize) -> Self {
+        self.max_fancy_checks_per_stage = Some(max);
+        self
+    }
+
+    /// Report an `X-Requested-With` package ID that is in neither
+    /// `client/hints/apps.yml` nor `client/hints/browsers.yml` as a
+    /// [`MobileApp`](crate::ClientType::MobileApp) client named after the
+    /// package (e.g. `com.example.internal`).  Only values that look like a
+    /// package ID (containing a `.`) qualify, so the `XMLHttpRequest` sent by
+    /// AJAX requests is ignored.  Off by default, matching Matomo.
+    pub fn unknown_xrw_as_app(mut self, enabled: bool) -> Self {
+        self.unknown_xrw_as_app = enabled;
+        self
+    }
+
+    /// Report laptops as [`Notebook`](crate::DeviceType::Notebook) rather
+    /// than `Desktop`: matches from `notebooks.yml` (whose entries say
+    /// `desktop`), and desktop-OS UAs carrying a laptop token (`Laptop`,
+    /// `MacBook`, `ThinkPad`, ...).  Off by default, matching Matomo.
+    pub fn prefer_notebook_type(mut self, enabled: bool) -> Self {
+        self.prefer_notebook_type = enabled;
+        self
+    }
+
+    /// Replace a non-Apple brand (and its model) with `Apple` when the OS is
+    /// an Apple OS, e.g. an iPhone UA that also carries an Android model
+    /// token.  The opposite case, an Apple brand on a non-Apple OS, is always
+    /// cleared.  Off by default, matching Matomo, which keeps the brand.
+    pub fn reset_inconsistent_brand(mut self, enabled: bool) -> Self {
+        self.reset_inconsistent_brand = enabled;
+        self
+    }
+
+    /// Treat a device file's prefilter hit as weak evidence of its default
+    /// type when none of its brands match, e.g. a camera marker on a UA from
+    /// an unlisted camera brand still yields a brandless `Camera`.
+    ///
+    /// Unlike [`DeviceFileSpec::claims_type`] later files are still tried,
+    /// and the weak type is only used when none of them match.  Files
+    /// without a prefilter (`mobiles.yml`) never contribute.  Off by
+    /// default, matching Matomo.
+    pub fn prefilter_implies_type(mut self, enabled: bool) -> Self {
+        self.prefilter_implies_type = enabled;
+        self
+    }
+
+    /// Add client names that classify the device as a TV, on top of the
+    /// built-in list (e.g. a regional TV browser).  Names must equal the
+    /// client name the dataset reports.  Calls accumulate.
+    pub fn with_tv_client_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tv_client_names
+            .extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Choose between the first matching device file (default) and the most
+    /// specific match across all of them.  See [`DeviceSelection`].
+    pub fn device_selection(mut self, selection: DeviceSelection) -> Self {
+        self.device_selection = selection;
+        self
+    }
+
+    /// Override how a device file is matched, or add a file of your own.
+    ///
+    /// A spec for one of Matomo's files (see
+    /// [`DeviceFileSpec::matomo_defaults`]) replaces it in place; any other
+    /// file is loaded from `regexes/device/` and tried after the built-in
+    /// ones.
+    pub fn device_file(mut self, spec: DeviceFileSpec) -> Self {
+        match self.device_files.iter_mut().find(|s| s.file == spec.file) {
+            Some(existing) => *existing = spec,
+            None => self.device_files.push(spec),
+        }
+        self
+    }
+
+    /// Insert a custom step into the detection pipeline, after the built-in
+    /// step named by [`DetectionStage::after`].  Unlike the post hook it runs
+    /// for borrowed and owned detections alike.
+    pub fn with_stage(mut self, stage: Box<dyn DetectionStage>) -> Self {
+        self.stages.push(Stage(Arc::from(stage)));
+        self
+    }
+
+    /// Register a callback that can rewrite every owned detection, e.g. to
+    /// reclassify internal test devices.
+    ///
+    /// The hook receives the UA and runs after all built-in heuristics.  It
+    /// only applies to [`DeviceDetector::parse_owned`] and
+    /// [`DeviceDetector::parse_owned_with_hints`]; the borrowed
+    /// [`DeviceDetector::parse`] path is left untouched.
+    pub fn with_post_hook(mut self, hook: Box<PostHookFn>) -> Self {
+        self.post_hook = Some(PostHook(Arc::from(hook)));
+        self
+    }
+
+    /// Rewrite every non-empty OS, client and engine version, e.g. to pad
+    /// versions to a fixed number of components for a storage backend.
+    ///
+    /// Runs once the detection is complete, so the built-in heuristics
+    /// (and custom stages) still see the versions from the UA.  Applies to
+    /// borrowed and owned detections alike.
+    pub fn with_version_formatter(mut self, formatter: Box<VersionFormatterFn>) -> Self {
+        self.version_formatter = Some(VersionFormatter(Arc::from(formatter)));
+        self
+    }
+
+    /// Load the Matomo YAML files and build the detector.
+    pub fn build(self) -> Result<DeviceDetector> {
+        DeviceDetector::from_builder(self)
+    }
+}
@@ -0,0 +1,138 @@
+use super::dataset_version::read_dataset_version;
+use super::db;
+use super::error::Result;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Every Matomo data file, deserialized but not yet compiled.
+///
+/// Produced from a `regexes/` directory ([`Dataset::load`]), an embedded
+/// copy of one (the `include_dir` feature), or the tables generated at build
+/// time by the `precompiled` feature.
+pub(crate) struct Dataset {
+    pub bots: Vec<db::BotEntry>,
+    pub oss: Vec<db::OsEntry>,
+    pub browsers: Vec<db::BrowserEntry>,
+    pub feed_readers: Vec<db::BrowserEntry>,
+    pub mobile_apps: Vec<db::BrowserEntry>,
+    pub libraries: Vec<db::BrowserEntry>,
+    pub media_players: Vec<db::BrowserEntry>,
+    pub pims: Vec<db::BrowserEntry>,
+    pub engines: Vec<db::EngineEntry>,
+    pub vendor_fragments: db::VendorFragmentMap,
+    pub app_hints: db::HintMap,
+    pub browser_hints: db::HintMap,
+    /// Device file name → brands.  Absent files have no entry.
+    pub devices: HashMap<&'static str, db::DeviceBrandMap>,
+    pub version: Option<String>,
+}
+
+/// Where [`Dataset::from_yaml_sources`] reads the data files from.  Paths
+/// are relative to the `regexes/` root, with `/` separators.
+pub(crate) trait YamlSource: Sync {
+    fn read(&self, path: &str) -> Result<String>;
+    fn exists(&self, path: &str) -> bool;
+    /// Matomo release of the data, if the source records it.
+    fn version(&self) -> Option<String>;
+}
+
+impl YamlSource for Path {
+    fn read(&self, path: &str) -> Result<String> {
+        Ok(std::fs::read_to_string(self.join(path))?)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.join(path).exists()
+    }
+
+    fn version(&self) -> Option<String> {
+        read_dataset_version(self)
+    }
+}
+
+#[cfg(feature = "include_dir")]
+impl YamlSource for include_dir::Dir<'_> {
+    fn read(&self, path: &str) -> Result<String> {
+        let file = self.get_file(self.path().join(path)).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{path} is not in the embedded directory"),
+            )
+        })?;
+        let content = file.contents_utf8().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{path} is not UTF-8"),
+            )
+        })?;
+        Ok(content.to_string())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.get_file(self.path().join(path)).is_some()
+    }
+
+    /// Only a `VERSION` file is consulted; there is no checkout around an
+    /// embedded directory to find `DeviceDetector.php` in.
+    fn version(&self) -> Option<String> {
+        let version = self.read("VERSION").ok()?;
+        let version = version.trim();
+        (!version.is_empty()).then(|| version.to_string())
+    }
+}
+
+impl Dataset {
+    /// Read every YAML file under `dir`, plus `device_files` from
+    /// `dir/device/`.  Missing device files are an error only when `strict`
+    /// is set.
+    pub fn load(dir: &Path, strict: bool, device_files: &[&'static str]) -> Result<Self> {
+        Self::from_yaml_sources(dir, strict, device_files)
+    }
+
+    /// [`load`](Self::load) over any [`YamlSource`].
+    pub fn from_yaml_sources(
+        source: &(impl YamlSource + ?Sized),
+        strict: bool,
+        device_files: &[&'static str],
+    ) -> Result<Self> {
+        let device_path = |file: &str| format!("device/{file}");
+
+        // mobiles.yml dominates load time, so parse device files alongside
+        // the flat lists.
+        let (devices, browsers) = rayon::join(
+            || -> Result<HashMap<_, _>> {
+                device_files
+                    .par_iter()
+                    .filter(|file| strict || source.exists(&device_path(file)))
+                    .map(|&file| Ok((file, load_yaml(source, &device_path(file))?)))
+                    .collect()
+            },
+            || load_yaml(source, "client/browsers.yml"),
+        );
+
+        Ok(Self {
+            bots: load_yaml(source, "bots.yml")?,
+            oss: load_yaml(source, "oss.yml")?,
+            browsers: browsers?,
+            feed_readers: load_yaml(source, "client/feed_readers.yml")?,
+            mobile_apps: load_yaml(source, "client/mobile_apps.yml")?,
+            libraries: load_yaml(source, "client/libraries.yml")?,
+            media_players: load_yaml(source, "client/mediaplayers.yml")?,
+            pims: load_yaml(source, "client/pim.yml")?,
+            engines: load_yaml(source, "client/browser_engine.yml")?,
+            vendor_fragments: load_yaml(source, "vendorfragments.yml")?,
+            app_hints: load_yaml(source, "client/hints/apps.yml")?,
+            browser_hints: load_yaml(source, "client/hints/browsers.yml")?,
+            devices: devices?,
+            version: source.version(),
+        })
+    }
+}
+
+fn load_yaml<T: serde::de::DeserializeOwned>(
+    source: &(impl YamlSource + ?Sized),
+    path: &str,
+) -> Result<T> {
+    Ok(serde_yaml::from_str(&source.read(path)?)?)
+}
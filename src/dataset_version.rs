@@ -0,0 +1,22 @@
+use std::path::Path;
+
+/// Matomo release of the regexes in `dir`: a `VERSION` file inside it, or
+/// else the `VERSION` constant of the checkout's `DeviceDetector.php`.
+///
+/// Also included by `build.rs` for the `precompiled` feature.
+pub(crate) fn read_dataset_version(dir: &Path) -> Option<String> {
+    if let Ok(content) = std::fs::read_to_string(dir.join("VERSION")) {
+        let version = content.trim();
+        if !version.is_empty() {
+            return Some(version.to_string());
+        }
+    }
+
+    // `public const VERSION = '6.4.1';` in Matomo's DeviceDetector.php.
+    let php = std::fs::read_to_string(dir.parent()?.join("DeviceDetector.php")).ok()?;
+    let rest = &php[php.find("const VERSION")?..];
+    let rest = rest[rest.find('=')? + 1..].trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let rest = &rest[1..];
+    Some(rest[..rest.find(quote)?].to_string())
+}
@@ -1,25 +1,51 @@
+use super::browser_extensions::BROWSER_EXTENSIONS;
+use super::builder::{DeviceDetectorBuilder, PostHook, VersionFormatter};
+use super::dataset::Dataset;
 use super::db;
 use super::device_prefilter::DevicePrefilter;
 use super::error::Result;
 use super::helpers::*;
+use super::http_libraries::HTTP_LIBRARIES;
 use super::os_helpers::*;
 use super::parser::{
-    compile_regex, full_pattern, CompiledEntry, CompiledParser, DeviceBrandParser,
+    compile_regex, BrandMatchResult, CompiledEntry, CompiledParser, DeviceBrandParser, MatchResult,
+    ParserOptions,
 };
 use super::parser_data::*;
+#[cfg(feature = "precompiled")]
+use super::precompiled;
+use super::stage::{PipelineStage, Stage};
 use super::substitution::substitute;
+#[cfg(feature = "timing")]
+use super::timing;
+use super::tv_clients::TV_CLIENTS;
 use super::types::*;
+use super::validate;
 use fancy_regex::Regex;
 use rayon::prelude::*;
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
+/// `(file, default_type, prefilter, claims_type, brand_parser)`; see
+/// `DeviceDetector::device_parsers`.
+type DeviceParserEntry = (
+    &'static str,
+    DeviceType,
+    DevicePrefilter,
+    bool,
+    DeviceBrandParser<DeviceBrandData, DeviceModelData>,
+);
+
 /// Pre-compiled regexes for heuristic device-type checks in `parse_with_hints()`.
 /// Each field corresponds to one `ua_matches()` callsite; compiling them once at
 /// init time avoids ~16 regex compilations per lookup.
 struct HeuristicRegexes {
     vr: Regex,
     chrome_android: Regex,
+    chrome_major: Regex,
+    firefox_android: Regex,
+    tablet_token: Regex,
     mobile_elibom: Regex,
     pad_apad: Regex,
     android_tablet: Regex,
@@ -31,9 +57,35 @@ struct HeuristicRegexes {
     puffin_tablet: Regex,
     opera_tv: Regex,
     android_tv: Regex,
+    mi_set_top: Regex,
+    set_top_box: Regex,
     smart_tv_tizen: Regex,
     tv_fragment: Regex,
     desktop_fragment: Regex,
+    cast_display: Regex,
+    cast_speaker: Regex,
+    show_mode: Regex,
+    homepod: Regex,
+    android_emulator: Regex,
+    platform_arm: Regex,
+    platform_loongarch: Regex,
+    platform_mips: Regex,
+    platform_superh: Regex,
+    platform_sparc: Regex,
+    platform_x64: Regex,
+    platform_x86: Regex,
+    kindle: Regex,
+    fire_tv: Regex,
+    kobo: Regex,
+    laptop: Regex,
+    non_apple_os: Regex,
+    reader_mode: Regex,
+    private_mode: Regex,
+    compat_view: Regex,
+    channel_canary: Regex,
+    channel_nightly: Regex,
+    channel_dev: Regex,
+    channel_beta: Regex,
 }
 
 impl HeuristicRegexes {
@@ -46,6 +98,9 @@ impl HeuristicRegexes {
         Ok(Self {
             vr: mk(r"Android( [.0-9]+)?; Mobile VR;| VR ")?,
             chrome_android: mk(r"Chrome/[.0-9]*")?,
+            chrome_major: mk(r"Chrome/(\d+)")?,
+            firefox_android: mk(r"Firefox/[.0-9]*")?,
+            tablet_token: mk("Tablet")?,
             mobile_elibom: mk(r"(?:Mobile|eliboM)")?,
             pad_apad: mk(r"Pad/APad")?,
             android_tablet: mk(r"Android( [.0-9]+)?; Tablet;|Tablet(?! PC)|.*\-tablet$")?,
@@ -57,9 +112,41 @@ impl HeuristicRegexes {
             puffin_tablet: mk(r"Puffin/(?:\d+[.\d]+)[AILW]T")?,
             opera_tv: mk(r"Opera TV Store| OMI/")?,
             android_tv: mk(r"Andr0id|(?:Android(?: UHD)?|Google) TV|\(lite\) TV|BRAVIA|Firebolt| TV$")?,
+            mi_set_top: mk(r"MiBOX|MiTV")?,
+            set_top_box: mk(
+                r"AOSP on p2\d\d|(?:X96|H96|T95|TX[36]|MXQ)(?: ?(?:Max|Pro|Mini|Plus))?\b",
+            )?,
             smart_tv_tizen: mk(r"SmartTV|Tizen.+ TV .+$")?,
             tv_fragment: mk(r"\(TV;")?,
             desktop_fragment: mk(r"Desktop(?: (?:x(?:32|64)|WOW64))?;")?,
+            cast_display: mk(r"(?:Google )?(?:Nest|Home) Hub|SmartDisplay")?,
+            cast_speaker: mk(
+                r"(?:Google )?Nest (?:Audio|Mini)|Google Home(?: Mini| Max)?|SmartSpeaker",
+            )?,
+            show_mode: mk(r"Show ?Mode")?,
+            homepod: mk(r"HomePod|AudioAccessory\d|audioOS")?,
+            android_emulator: mk(r"Android-x86|BlueStacks")?,
+            platform_arm: mk(
+                r"arm[ _;)ev]|.*arm$|.*arm64|aarch64|Apple ?TV|Watch ?OS|Watch1,[12]",
+            )?,
+            platform_loongarch: mk("loongarch64")?,
+            platform_mips: mk("mips")?,
+            platform_superh: mk("sh4")?,
+            platform_sparc: mk("sparc64")?,
+            platform_x64: mk(r"64-?bit|WOW64|(?:Intel)?x64|WINDOWS_64|win64|.*amd64|.*x86_64")?,
+            platform_x86: mk(r".*32bit|.*win32|(?:i[0-9]|x)86|i86pc")?,
+            kindle: mk(r"Kindle/\d")?,
+            fire_tv: mk(r"AFT[A-Z0-9]+[);/ ]")?,
+            kobo: mk(r"Kobo ?(?:Touch|eReader)\b")?,
+            laptop: mk(r"(?:Laptop|Notebook|MacBook|ThinkPad|IdeaPad|Chromebook)\b")?,
+            non_apple_os: mk(r"Windows (?:NT|Phone)|(?<!like )Android")?,
+            reader_mode: mk(r"Reader ?Mode|ReadingView")?,
+            private_mode: mk(r"Secret ?Mode|Incognito|Private ?Browsing")?,
+            compat_view: mk(r"MSIE 7\.0;.*Trident/[4-8]\.0")?,
+            channel_canary: mk(r"Canary")?,
+            channel_nightly: mk(r"Nightly|Firefox/\d+\.\d+a1\b")?,
+            channel_dev: mk(r"Firefox/\d+\.\d+a2\b|(?:Chrome|Edge) Dev\b")?,
+            channel_beta: mk(r"Firefox/\d+\.\d+b\d+|(?:Chrome|Edge|Opera) Beta\b")?,
         })
     }
 }
@@ -73,19 +160,41 @@ pub struct DeviceDetector {
     library_parser: CompiledParser<ClientData>,
     media_player_parser: CompiledParser<ClientData>,
     pim_parser: CompiledParser<ClientData>,
+    extension_parser: CompiledParser<ClientData>,
+    library_supplement_parser: CompiledParser<ClientData>,
     engine_parser: CompiledParser<EngineData>,
+    /// Engine name → version extractors, tried in order (Matomo's `EngineVersion`).
+    engine_version_regexes: HashMap<String, Vec<Regex>>,
     vendor_fragment_parser: CompiledParser<VendorFragmentData>,
-    /// Each device parser tuple: (default_type, prefilter, claims_type, brand_parser).
+    /// Each device parser tuple: (file, default_type, prefilter, claims_type, brand_parser).
     ///
     /// `claims_type`: when `true`, a prefilter match claims the device type even
     /// if no brand regex matches.  This mirrors Matomo's HbbTv/ShellTv parsers
     /// which always set device_type=TV when their marker is present.
-    device_parsers: Vec<(
-        DeviceType,
-        DevicePrefilter,
-        bool,
-        DeviceBrandParser<DeviceBrandData, DeviceModelData>,
-    )>,
+    device_parsers: Vec<DeviceParserEntry>,
+    /// Run full detection even when a bot matched.
+    bots_dont_short_circuit: bool,
+    /// Report unlisted X-Requested-With packages as mobile apps.
+    unknown_xrw_as_app: bool,
+    /// Type laptops as `Notebook` instead of `Desktop`.
+    prefer_notebook_type: bool,
+    /// Rebrand non-Apple devices on an Apple OS as `Apple`.
+    reset_inconsistent_brand: bool,
+    /// Use an unclaimed prefilter hit as a fallback device type.
+    prefilter_implies_type: bool,
+    /// Client names that classify the device as a TV.
+    tv_clients: HashSet<String>,
+    /// First-match vs most-specific device file selection.
+    device_selection: DeviceSelection,
+    /// Custom pipeline steps, in registration order.
+    stages: Vec<Stage>,
+    /// User callback applied to owned detections.
+    post_hook: Option<PostHook>,
+    version_formatter: Option<VersionFormatter>,
+    /// Matomo release the regexes come from, if it could be determined.
+    dataset_version: Option<String>,
+    /// Device files skipped because they were absent (non-strict builds only).
+    missing_device_files: Vec<&'static str>,
     /// Pre-compiled heuristic regexes for device-type inference.
     heuristic_regexes: HeuristicRegexes,
     /// Package-ID → app name (from `client/hints/apps.yml`).
@@ -100,50 +209,140 @@ impl DeviceDetector {
     /// `dir` should point to the `regexes/` directory of a Matomo device-detector
     /// checkout (containing `bots.yml`, `oss.yml`, `client/`, `device/`, etc.).
     pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
-        let dir = dir.as_ref();
-        let client_dir = dir.join("client");
-        let device_dir = dir.join("device");
+        Self::builder(dir).build()
+    }
+
+    /// Build the detector from the dataset embedded at compile time by the
+    /// `precompiled` feature, with default builder options.
+    ///
+    /// `build.rs` reads the regexes from `DEVICE_DETECTOR_REGEXES` (default
+    /// `vendor/device-detector/regexes`) and emits them as static tables, so
+    /// no YAML is parsed at runtime; only the regexes are compiled.  The
+    /// generated source for a full Matomo checkout is several megabytes and
+    /// adds noticeably to the crate's compile time.
+    #[cfg(feature = "precompiled")]
+    pub fn precompiled() -> Result<Self> {
+        Self::from_dataset(precompiled::dataset(), DeviceDetectorBuilder::new(""))
+    }
+
+    /// Build the detector from a `regexes/` directory embedded with
+    /// [`include_dir::include_dir!`], with default builder options.
+    ///
+    /// Unlike [`precompiled`](Self::precompiled) the YAML is still parsed at
+    /// runtime, but nothing is read from the filesystem.  The dataset version
+    /// comes from a `VERSION` file in `dir`, if there is one.
+    #[cfg(feature = "include_dir")]
+    pub fn from_include_dir(dir: &include_dir::Dir<'_>) -> Result<Self> {
+        let builder = DeviceDetectorBuilder::new("");
+        let device_files: Vec<_> = builder.device_files.iter().map(|s| s.file).collect();
+        let dataset = Dataset::from_yaml_sources(dir, builder.strict, &device_files)?;
+        Self::from_dataset(dataset, builder)
+    }
+
+    /// Check a Matomo `regexes/` directory without building a detector.
+    ///
+    /// Every YAML file is loaded and every regex compiled; unreadable files,
+    /// YAML errors and regexes neither engine accepts are returned.  An empty
+    /// list means [`from_dir`](Self::from_dir) should succeed.
+    pub fn validate_dir(dir: impl AsRef<Path>) -> Vec<DatasetIssue> {
+        validate::validate_dir(dir.as_ref())
+    }
+
+    /// Start configuring a detector that loads its data from `dir`.
+    pub fn builder(dir: impl AsRef<Path>) -> DeviceDetectorBuilder {
+        DeviceDetectorBuilder::new(dir)
+    }
+
+    /// Version of the loaded Matomo dataset.
+    ///
+    /// Read from a `VERSION` file inside the regexes directory, or else from
+    /// the `VERSION` constant in the checkout's `DeviceDetector.php` (the
+    /// parent of `regexes/`).  `None` when neither is present.
+    pub fn dataset_version(&self) -> Option<&str> {
+        self.dataset_version.as_deref()
+    }
+
+    /// Device files that were absent from `regexes/device/` and skipped
+    /// (always empty for a [`strict`](DeviceDetectorBuilder::strict) build).
+    pub fn missing_device_files(&self) -> &[&'static str] {
+        &self.missing_device_files
+    }
+
+    pub(crate) fn from_builder(builder: DeviceDetectorBuilder) -> Result<Self> {
+        let device_files: Vec<_> = builder.device_files.iter().map(|s| s.file).collect();
+        let dataset = Dataset::load(&builder.dir, builder.strict, &device_files)?;
+        Self::from_dataset(dataset, builder)
+    }
+
+    fn from_dataset(dataset: Dataset, builder: DeviceDetectorBuilder) -> Result<Self> {
+        let options = ParserOptions {
+            standard_only: builder.standard_regex_only,
+            literal_min_len: builder.literal_min_len,
+            max_fancy_checks: builder.max_fancy_checks_per_stage.unwrap_or(usize::MAX),
+        };
+        let options = &options;
+        let device_files = builder.device_files.clone();
+        let Dataset {
+            bots,
+            oss,
+            browsers,
+            feed_readers,
+            mobile_apps,
+            libraries,
+            media_players,
+            pims,
+            engines,
+            vendor_fragments,
+            app_hints,
+            browser_hints,
+            mut devices,
+            version: dataset_version,
+        } = dataset;
 
         // Build flat-list parsers and device parsers concurrently.
         let (flat_result, device_parsers_result) = rayon::join(
             || -> Result<_> {
                 // Bots
-                let bots: Vec<db::BotEntry> = load_yaml(&dir.join("bots.yml"))?;
-                let bot_parser = CompiledParser::build(bots.into_iter().map(|b| {
-                    (
-                        b.regex,
-                        BotData {
-                            name: b.name,
-                            category: b.category,
-                            url: b.url,
-                            producer: b.producer,
-                        },
-                    )
-                }))?;
+                let bot_parser = CompiledParser::build(
+                    bots.into_iter().map(|b| {
+                        (
+                            b.regex,
+                            BotData {
+                                name: b.name,
+                                category: b.category,
+                                url: b.url,
+                                producer: b.producer,
+                            },
+                        )
+                    }),
+                    options,
+                )?;
 
                 // OS
-                let oss: Vec<db::OsEntry> = load_yaml(&dir.join("oss.yml"))?;
-                let os_parser = CompiledParser::build(oss.into_iter().map(|o| {
-                    (
-                        o.regex,
-                        OsData {
-                            name: o.name,
-                            version_template: o.version,
-                        },
-                    )
-                }))?;
+                let os_parser = CompiledParser::build(
+                    oss.into_iter().map(|o| {
+                        (
+                            o.regex,
+                            OsData {
+                                name: o.name,
+                                version_template: o.version,
+                            },
+                        )
+                    }),
+                    options,
+                )?;
 
                 // Client parsers — build all 6 in parallel
                 let client_parsers: Vec<CompiledParser<ClientData>> = vec![
-                    ("browsers.yml", ClientType::Browser),
-                    ("feed_readers.yml", ClientType::FeedReader),
-                    ("mobile_apps.yml", ClientType::MobileApp),
-                    ("libraries.yml", ClientType::Library),
-                    ("mediaplayers.yml", ClientType::MediaPlayer),
-                    ("pim.yml", ClientType::Pim),
+                    (browsers, ClientType::Browser),
+                    (feed_readers, ClientType::FeedReader),
+                    (mobile_apps, ClientType::MobileApp),
+                    (libraries, ClientType::Library),
+                    (media_players, ClientType::MediaPlayer),
+                    (pims, ClientType::Pim),
                 ]
                 .into_par_iter()
-                .map(|(file, ct)| build_client_parser(&client_dir.join(file), ct))
+                .map(|(entries, ct)| build_client_parser(entries, ct, options))
                 .collect::<Result<Vec<_>>>()?;
 
                 let mut clients = client_parsers.into_iter();
@@ -154,19 +353,55 @@ impl DeviceDetector {
                 let media_player_parser = clients.next().unwrap();
                 let pim_parser = clients.next().unwrap();
 
+                // Browser extensions (built-in list, not part of Matomo's data)
+                let extension_parser = CompiledParser::build(
+                    BROWSER_EXTENSIONS.iter().map(|&(regex, name)| {
+                        (
+                            regex.to_string(),
+                            ClientData {
+                                kind: ClientType::BrowserExtension,
+                                name: name.to_string(),
+                                version_template: None,
+                                engine_default: None,
+                                engine_versions: None,
+                            },
+                        )
+                    }),
+                    options,
+                )?;
+
+                // HTTP libraries missing from libraries.yml (built-in list)
+                let library_supplement_parser = CompiledParser::build(
+                    HTTP_LIBRARIES.iter().map(|&(regex, name)| {
+                        (
+                            regex.to_string(),
+                            ClientData {
+                                kind: ClientType::Library,
+                                name: name.to_string(),
+                                version_template: None,
+                                engine_default: None,
+                                engine_versions: None,
+                            },
+                        )
+                    }),
+                    options,
+                )?;
+
                 // Browser engines
-                let engines: Vec<db::EngineEntry> =
-                    load_yaml(&client_dir.join("browser_engine.yml"))?;
+                let engine_version_regexes = engines
+                    .iter()
+                    .map(|e| Ok((e.name.clone(), engine_version_regexes(&e.name)?)))
+                    .collect::<Result<HashMap<_, _>>>()?;
                 let engine_parser = CompiledParser::build(
                     engines
                         .into_iter()
                         .map(|e| (e.regex, EngineData { name: e.name })),
+                    options,
                 )?;
 
                 // Vendor fragments
-                let vf_map: db::VendorFragmentMap = load_yaml(&dir.join("vendorfragments.yml"))?;
-                let vendor_fragment_parser =
-                    CompiledParser::build(vf_map.into_iter().flat_map(|(brand, patterns)| {
+                let vendor_fragment_parser = CompiledParser::build(
+                    vendor_fragments.into_iter().flat_map(|(brand, patterns)| {
                         // Each pattern gets `[^a-z0-9]+` appended (Matomo's VendorFragment.php).
                         patterns.into_iter().map(move |pat| {
                             (
@@ -176,7 +411,9 @@ impl DeviceDetector {
                                 },
                             )
                         })
-                    }))?;
+                    }),
+                    options,
+                )?;
 
                 Ok((
                     bot_parser,
@@ -187,122 +424,95 @@ impl DeviceDetector {
                     library_parser,
                     media_player_parser,
                     pim_parser,
+                    extension_parser,
+                    library_supplement_parser,
                     engine_parser,
+                    engine_version_regexes,
                     vendor_fragment_parser,
                 ))
             },
             || -> Result<_> {
-                // Device parsers — order preserved by par_iter collect.
+                // Device parsers, one per `DeviceFileSpec` — order preserved
+                // by par_iter collect.
                 //
-                // Each entry: (file, device_type, prefilter_kind)
-                // PrefilterKind mirrors Matomo's PHP device parser prefilters:
-                //   Specific  — UA must match a hardcoded regex (ShellTv, HbbTv, Notebook)
-                //   Overall   — build a mega-regex from all brand regexes (Console, etc.)
-                //   None      — always run (Mobiles)
-                #[derive(Clone)]
-                enum PrefilterKind {
-                    Specific(&'static str),
-                    Overall,
-                    None,
+                // Older Matomo checkouts lack some device files; the dataset
+                // only has them when present (or the build was strict), so
+                // skip those parsers instead of aborting the whole build.
+                let mut present = Vec::new();
+                let mut missing: Vec<&'static str> = Vec::new();
+                for spec in device_files {
+                    match devices.remove(spec.file) {
+                        Some(brands) => present.push((brands, spec)),
+                        None => missing.push(spec.file),
+                    }
                 }
 
-                //            (file, type, prefilter, claims_type)
-                // claims_type=true means the prefilter match alone claims the
-                // device type, preventing fallthrough (HbbTv/ShellTv → TV).
-                let specs: Vec<(&str, DeviceType, PrefilterKind, bool)> = vec![
-                    (
-                        "shell_tv.yml",
-                        DeviceType::Tv,
-                        PrefilterKind::Specific(r"(?i)[a-z]+[ _]Shell[ _]\w{6}|tclwebkit"),
-                        true,
-                    ),
-                    (
-                        "televisions.yml",
-                        DeviceType::Tv,
-                        PrefilterKind::Specific(r"(?i)(?:HbbTV|SmartTvA)/"),
-                        true,
-                    ),
-                    (
-                        "consoles.yml",
-                        DeviceType::Console,
-                        PrefilterKind::Overall,
-                        false,
-                    ),
-                    (
-                        "car_browsers.yml",
-                        DeviceType::CarBrowser,
-                        PrefilterKind::Overall,
-                        false,
-                    ),
-                    (
-                        "cameras.yml",
-                        DeviceType::Camera,
-                        PrefilterKind::Overall,
-                        false,
-                    ),
-                    (
-                        "portable_media_player.yml",
-                        DeviceType::PortableMediaPlayer,
-                        PrefilterKind::Overall,
-                        false,
-                    ),
-                    (
-                        "notebooks.yml",
-                        DeviceType::Notebook,
-                        PrefilterKind::Specific(r"FBMD/"),
-                        false,
-                    ),
-                    (
-                        "mobiles.yml",
-                        DeviceType::Smartphone,
-                        PrefilterKind::None,
-                        false,
-                    ),
-                ];
-
-                specs
+                let interner = Interner::default();
+                let parsers = present
                     .into_par_iter()
-                    .map(
-                        |(file, device_type, prefilter_kind, claims_type)| -> Result<_> {
-                            let (parser, brand_regexes) =
-                                build_device_brand_parser(&device_dir.join(file), device_type)?;
-
-                            let prefilter = match prefilter_kind {
-                                PrefilterKind::Specific(pat) => {
-                                    let re = fancy_regex::Regex::new(pat)?;
-                                    DevicePrefilter::Regex(re)
-                                }
-                                PrefilterKind::Overall => {
-                                    DevicePrefilter::build_overall_prefilter(&brand_regexes)?
-                                }
-                                PrefilterKind::None => DevicePrefilter::None,
-                            };
-
-                            Ok((device_type, prefilter, claims_type, parser))
-                        },
-                    )
-                    .collect::<Result<Vec<_>>>()
+                    .map(|(brands, spec)| -> Result<_> {
+                        let (parser, brand_regexes) =
+                            build_device_brand_parser(brands, &interner, options)?;
+
+                        let prefilter = match &spec.prefilter {
+                            DevicePrefilterKind::Specific(pat) => {
+                                let re = fancy_regex::Regex::new(pat)?;
+                                DevicePrefilter::Regex(re)
+                            }
+                            DevicePrefilterKind::Overall => {
+                                DevicePrefilter::build_overall_prefilter(&brand_regexes)?
+                            }
+                            DevicePrefilterKind::None => DevicePrefilter::None,
+                        };
+
+                        Ok((
+                            spec.file,
+                            spec.default_type,
+                            prefilter,
+                            spec.claims_type,
+                            parser,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok((parsers, missing))
             },
         );
 
         let (
-            bot_parser,
-            os_parser,
-            browser_parser,
-            feed_reader_parser,
-            mobile_app_parser,
-            library_parser,
-            media_player_parser,
-            pim_parser,
-            engine_parser,
-            vendor_fragment_parser,
+            mut bot_parser,
+            mut os_parser,
+            mut browser_parser,
+            mut feed_reader_parser,
+            mut mobile_app_parser,
+            mut library_parser,
+            mut media_player_parser,
+            mut pim_parser,
+            mut extension_parser,
+            mut library_supplement_parser,
+            mut engine_parser,
+            engine_version_regexes,
+            mut vendor_fragment_parser,
         ) = flat_result?;
-        let device_parsers = device_parsers_result?;
-
-        // Client hints lookup maps.
-        let hints_dir = client_dir.join("hints");
-        let app_hints: db::HintMap = load_yaml(&hints_dir.join("apps.yml"))?;
-        let browser_hints: db::HintMap = load_yaml(&hints_dir.join("browsers.yml"))?;
+        if builder.regex_set_gate {
+            bot_parser.enable_regex_set_gate();
+            os_parser.enable_regex_set_gate();
+            for parser in [
+                &mut browser_parser,
+                &mut feed_reader_parser,
+                &mut mobile_app_parser,
+                &mut library_parser,
+                &mut media_player_parser,
+                &mut pim_parser,
+                &mut extension_parser,
+                &mut library_supplement_parser,
+            ] {
+                parser.enable_regex_set_gate();
+            }
+            engine_parser.enable_regex_set_gate();
+            vendor_fragment_parser.enable_regex_set_gate();
+        }
+        let (device_parsers, missing_device_files) = device_parsers_result?;
 
         let heuristic_regexes = HeuristicRegexes::compile()?;
 
@@ -315,9 +525,28 @@ impl DeviceDetector {
             library_parser,
             media_player_parser,
             pim_parser,
+            extension_parser,
+            library_supplement_parser,
             engine_parser,
+            engine_version_regexes,
             vendor_fragment_parser,
             device_parsers,
+            dataset_version,
+            missing_device_files,
+            bots_dont_short_circuit: builder.bots_dont_short_circuit,
+            unknown_xrw_as_app: builder.unknown_xrw_as_app,
+            prefer_notebook_type: builder.prefer_notebook_type,
+            reset_inconsistent_brand: builder.reset_inconsistent_brand,
+            prefilter_implies_type: builder.prefilter_implies_type,
+            tv_clients: TV_CLIENTS
+                .iter()
+                .map(|name| name.to_string())
+                .chain(builder.tv_client_names)
+                .collect(),
+            device_selection: builder.device_selection,
+            stages: builder.stages,
+            post_hook: builder.post_hook,
+            version_formatter: builder.version_formatter,
             heuristic_regexes,
             app_hints,
             browser_hints,
@@ -332,32 +561,232 @@ impl DeviceDetector {
         self.parse_with_hints(ua, None)
     }
 
+    /// [`parse`](Self::parse) plus a [`DetectionMeta`] of plain integers
+    /// identifying the dataset entries behind it, for logging numeric
+    /// fingerprints without allocating.
+    pub fn parse_with_meta<'a>(&'a self, ua: &'a str) -> (Detection<'a>, DetectionMeta) {
+        self.parse_with_hints_meta(ua, None)
+    }
+
+    /// [`parse_with_meta`](Self::parse_with_meta) with optional client hints.
+    pub fn parse_with_hints_meta<'a>(
+        &'a self,
+        ua: &'a str,
+        hints: Option<&ClientHints>,
+    ) -> (Detection<'a>, DetectionMeta) {
+        let detection = self.parse_with_hints(ua, hints);
+        let meta = DetectionMeta::new(&detection.rule_ids, hints.is_some());
+        (detection, meta)
+    }
+
+    /// [`parse_with_hints`](Self::parse_with_hints), also reporting how long
+    /// it took and how many strings it allocated, to judge e.g. whether a
+    /// cache in front of the detector pays off for a class of UAs.
+    #[cfg(feature = "timing")]
+    pub fn parse_timed<'a>(
+        &'a self,
+        ua: &'a str,
+        hints: Option<&ClientHints>,
+    ) -> (Detection<'a>, TimingSnapshot) {
+        let allocations = timing::owned_allocations();
+        let start = std::time::Instant::now();
+        let detection = self.parse_with_hints(ua, hints);
+        let snapshot = TimingSnapshot {
+            elapsed: start.elapsed(),
+            owned_allocations: timing::owned_allocations() - allocations,
+        };
+        (detection, snapshot)
+    }
+
+    /// Parse a User-Agent string into an owned [`DetectionOwned`], running the
+    /// post hook registered with
+    /// [`DeviceDetectorBuilder::with_post_hook`] if any.
+    pub fn parse_owned(&self, ua: &str) -> DetectionOwned {
+        self.parse_owned_with_hints(ua, None)
+    }
+
+    /// [`parse_owned`](Self::parse_owned) with optional client hints.
+    pub fn parse_owned_with_hints(&self, ua: &str, hints: Option<&ClientHints>) -> DetectionOwned {
+        let mut detection = self.parse_with_hints(ua, hints).into_owned();
+        if let Some(PostHook(hook)) = &self.post_hook {
+            hook(ua, &mut detection);
+        }
+        detection
+    }
+
+    /// [`parse_owned_with_hints`](Self::parse_owned_with_hints) taking the
+    /// hints by value, for one-shot detection with hints built inline.
+    pub fn parse_owned_hints(&self, ua: &str, hints: ClientHints) -> DetectionOwned {
+        self.parse_owned_with_hints(ua, Some(&hints))
+    }
+
+    /// Just the client type, for routing decisions (mobile-app API vs web)
+    /// that don't need the rest of the detection.
+    ///
+    /// Runs the client parsers only, skipping version substitution and
+    /// engine resolution.  Bot detection, client hints and custom stages are
+    /// not applied, so a bot UA reports the type of the client it imitates.
+    pub fn detect_client_type(&self, ua: &str) -> Option<ClientType> {
+        self.client_parsers()
+            .into_iter()
+            .find_map(|(parser, _)| parser.match_first(ua))
+            .map(|m| m.data.kind)
+    }
+
+    /// Just the producer of the bot `ua` belongs to, for allow-lists keyed on
+    /// the organisation rather than the bot name.  `None` for non-bots and
+    /// for bots.yml entries without producer data.  Custom stages are not
+    /// applied.
+    pub fn detect_bot_producer<'a>(&'a self, ua: &'a str) -> Option<BotProducer<'a>> {
+        let producer = self.bot_parser.match_first(ua)?.data.producer.as_ref()?;
+        Some(BotProducer {
+            name: producer.name.as_deref(),
+            url: producer.url.as_deref(),
+        })
+    }
+
+    /// Just the device brand, for attribution flows that don't need the rest
+    /// of the detection.
+    ///
+    /// Runs the device parsers plus the vendor-fragment, e-reader and Apple
+    /// fallbacks, but neither bot detection nor the device-type heuristics,
+    /// so brands those heuristics assign (e.g. Samsung for bare Tizen UAs)
+    /// are not reported.  Android emulators report no brand, as in
+    /// [`parse`](Self::parse).  Returns the raw dataset brand string.
+    pub fn detect_brand<'a>(&'a self, ua: &'a str) -> Option<&'a str> {
+        if self
+            .heuristic_regexes
+            .android_emulator
+            .is_match(ua)
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        // Device brands always borrow from the dataset.
+        let mut brand = match self.detect_device(ua).map(|(d, _)| d.brand) {
+            Some(Cow::Borrowed(b)) if b != "Unknown" => b,
+            _ => "",
+        };
+
+        if brand.is_empty() {
+            if let Some(m) = self.vendor_fragment_parser.match_first(ua) {
+                brand = m.data.brand.as_str();
+            }
+        }
+
+        if brand.is_empty() {
+            let hr = &self.heuristic_regexes;
+            if hr.kindle.is_match(ua).unwrap_or(false) {
+                brand = "Amazon";
+            } else if hr.kobo.is_match(ua).unwrap_or(false) {
+                brand = "Rakuten Kobo";
+            }
+        }
+
+        let is_apple_os = self
+            .os_parser
+            .match_first(ua)
+            .is_some_and(|m| is_apple_os(&substitute(&m.data.name, &m.captures)))
+            && !self
+                .heuristic_regexes
+                .non_apple_os
+                .is_match(ua)
+                .unwrap_or(false);
+        if brand == "Apple" && !is_apple_os {
+            brand = "";
+        }
+        if is_apple_os && (brand.is_empty() || self.reset_inconsistent_brand) {
+            brand = "Apple";
+        }
+
+        (!brand.is_empty()).then_some(brand)
+    }
+
     /// Parse a User-Agent string with optional client hints and return detection results.
     pub fn parse_with_hints<'a>(
         &'a self,
         ua: &'a str,
         hints: Option<&ClientHints>,
     ) -> Detection<'a> {
+        let mut detection = self.run_pipeline(ua, hints, true);
+        self.format_versions(&mut detection);
+        detection
+    }
+
+    /// [`parse_with_hints`](Self::parse_with_hints) without device detection,
+    /// for callers that only need the OS and client distribution.
+    ///
+    /// Skips the device files and the device-type heuristics, by far the
+    /// most expensive part of a parse, so `device` is always `None`.  Bot,
+    /// OS and client results are identical to a full parse; custom stages
+    /// placed after [`PipelineStage::Device`] or
+    /// [`PipelineStage::Heuristics`] do not run.
+    pub fn parse_without_device<'a>(
+        &'a self,
+        ua: &'a str,
+        hints: Option<&ClientHints>,
+    ) -> Detection<'a> {
+        let mut detection = self.run_pipeline(ua, hints, false);
+        self.format_versions(&mut detection);
+        detection
+    }
+
+    /// Apply the formatter registered with
+    /// [`DeviceDetectorBuilder::with_version_formatter`], if any.
+    fn format_versions(&self, detection: &mut Detection<'_>) {
+        let Some(VersionFormatter(format)) = &self.version_formatter else {
+            return;
+        };
+        let apply = |version: &mut Cow<'_, str>| {
+            if !version.is_empty() {
+                *version = owned(format(version));
+            }
+        };
+        if let Some(os) = detection.os.as_mut() {
+            apply(&mut os.version);
+        }
+        if let Some(client) = detection.client.as_mut() {
+            apply(&mut client.version);
+            apply(&mut client.engine_version);
+        }
+    }
+
+    fn run_pipeline<'a>(
+        &'a self,
+        ua: &'a str,
+        hints: Option<&ClientHints>,
+        with_device: bool,
+    ) -> Detection<'a> {
+        let mut rule_ids = RuleIds::default();
+
         // 1. Bot check
-        if let Some(m) = self.bot_parser.match_first(ua) {
-            return Detection {
-                bot: Some(Bot {
-                    name: substitute(&m.data.name, &m.captures),
-                    category: m.data.category.as_deref(),
-                    url: m.data.url.as_deref(),
-                    producer: m.data.producer.as_ref().map(|p| BotProducer {
-                        name: p.name.as_deref(),
-                        url: p.url.as_deref(),
-                    }),
-                }),
-                os: None,
-                client: None,
-                device: None,
-            };
+        let mut bot = self.bot_parser.match_first(ua).map(|m| {
+            rule_ids.bot = Some(RuleId {
+                file: "bots.yml",
+                index: m.index,
+                model: None,
+            });
+            bot_from_match(m)
+        });
+        let mut partial = Detection {
+            bot,
+            rule_ids,
+            ..Default::default()
+        };
+        self.run_stages(PipelineStage::Bot, ua, hints, &mut partial);
+        if partial.bot.is_some() && !self.bots_dont_short_circuit {
+            return partial;
         }
+        Detection { bot, rule_ids, .. } = partial;
 
         // 2. OS detection
         let os = self.os_parser.match_first(ua).map(|m| {
+            rule_ids.os = Some(RuleId {
+                file: "oss.yml",
+                index: m.index,
+                model: None,
+            });
             let version = match &m.data.version_template {
                 Some(tpl) => substitute(tpl, &m.captures),
                 None => capture_or_empty(&m.captures, 1),
@@ -365,14 +794,58 @@ impl DeviceDetector {
             Os {
                 name: substitute(&m.data.name, &m.captures),
                 version,
+                platform: Cow::Borrowed(self.detect_platform(ua, hints)),
+                version_inferred: false,
             }
         });
+        let ua_os_name = os.as_ref().map(|o| o.name.clone());
+        let mut os = self.apply_platform_hint(os, ua, hints);
+        if os.as_ref().map(|o| &o.name) != ua_os_name.as_ref() {
+            // The platform hint, not the dataset, named the OS.
+            rule_ids.os = None;
+        }
+
+        // Android UAs stripped of their version token: fall back to the
+        // oldest Android release the embedded Chrome version supports.
+        if let Some(o) = os
+            .as_mut()
+            .filter(|o| o.name == "Android" && o.version.is_empty())
+        {
+            let chrome_major = self
+                .heuristic_regexes
+                .chrome_major
+                .captures(ua)
+                .ok()
+                .flatten()
+                .and_then(|caps| caps.get(1)?.as_str().parse().ok());
+            if let Some(major) = chrome_major {
+                o.version = Cow::Borrowed(android_version_for_chrome(major));
+                o.version_inferred = true;
+            }
+        }
+        let mut partial = Detection {
+            bot,
+            os,
+            rule_ids,
+            ..Default::default()
+        };
+        self.run_stages(PipelineStage::Os, ua, hints, &mut partial);
+        Detection {
+            bot,
+            os,
+            rule_ids,
+            ..
+        } = partial;
 
         // 3. Client detection (try each client parser in order)
-        let mut client = self.detect_client(ua);
+        let (mut client, client_rule) = self.detect_client(ua).unzip();
+        rule_ids.client = client_rule;
 
         // 4. X-Requested-With client override from hints.
-        if let Some(xrw) = hints.and_then(|h| h.x_requested_with.as_deref()) {
+        if let Some(xrw) = hints
+            .and_then(|h| h.x_requested_with.as_deref())
+            .map(|xrw| self.xrw_package(xrw))
+        {
             if let Some(app_name) = self.app_hints.get(xrw) {
                 let keep_version = client
                     .as_ref()
@@ -382,12 +855,15 @@ impl DeviceDetector {
                 } else {
                     Cow::Borrowed("")
                 };
+                rule_ids.client = None;
                 client = Some(Client {
                     kind: ClientType::MobileApp,
-                    name: Cow::Owned(app_name.clone()),
+                    name: owned(app_name.clone()),
                     version,
                     engine: Cow::Borrowed(""),
                     engine_version: Cow::Borrowed(""),
+                    mode: None,
+                    channel: None,
                 });
             } else if let Some(browser_name) = self.browser_hints.get(xrw) {
                 let keep_version = client
@@ -403,18 +879,106 @@ impl DeviceDetector {
                 } else {
                     (Cow::Borrowed(""), Cow::Borrowed(""), Cow::Borrowed(""))
                 };
+                rule_ids.client = None;
                 client = Some(Client {
                     kind: ClientType::Browser,
-                    name: Cow::Owned(browser_name.clone()),
+                    name: owned(browser_name.clone()),
                     version,
                     engine,
                     engine_version,
+                    mode: None,
+                    channel: None,
+                });
+            } else if self.unknown_xrw_as_app && xrw.contains('.') {
+                rule_ids.client = None;
+                client = Some(Client {
+                    kind: ClientType::MobileApp,
+                    name: owned(xrw.to_string()),
+                    version: Cow::Borrowed(""),
+                    engine: Cow::Borrowed(""),
+                    engine_version: Cow::Borrowed(""),
+                    mode: None,
+                    channel: None,
                 });
             }
         }
 
+        // Reduced UAs freeze the browser version to `MAJOR.0.0.0`; the legacy
+        // `Sec-CH-UA-Full-Version` hint has the real one.  It describes the
+        // Chromium brand, so it is only taken when the majors agree.
+        if let Some(full_version) = hints
+            .and_then(|h| h.full_version.as_deref())
+            .map(|v| v.trim_matches('"'))
+            .filter(|v| !v.is_empty())
+        {
+            if let Some(c) = client.as_mut().filter(|c| c.kind == ClientType::Browser) {
+                let major = |v: &str| v.split('.').next().unwrap_or("").to_owned();
+                if c.version.is_empty() || major(&c.version) == major(full_version) {
+                    c.version = owned(full_version.to_string());
+                }
+            }
+        }
+
+        // Browsing-mode tokens some clients append (reader view, private
+        // browsing), or IE's compatibility view.
+        if let Some(c) = client.as_mut() {
+            c.mode = self.client_mode(ua);
+            if c.kind == ClientType::Browser {
+                c.channel = self.client_channel(ua);
+            }
+        }
+        let mut partial = Detection {
+            bot,
+            os,
+            client,
+            rule_ids,
+            ..Default::default()
+        };
+        self.run_stages(PipelineStage::Client, ua, hints, &mut partial);
+        Detection {
+            bot,
+            os,
+            client,
+            rule_ids,
+            ..
+        } = partial;
+
+        if !with_device {
+            let is_android_family = os.as_ref().is_some_and(|o| is_android_os(&o.name));
+            let hr = &self.heuristic_regexes;
+            let emulator = hr.android_emulator.is_match(ua).unwrap_or(false);
+            return Detection {
+                bot,
+                os,
+                client,
+                device: None,
+                android_webview: is_android_family && ua.contains("; wv)"),
+                emulator,
+                rule_ids,
+            };
+        }
+
         // 5. Device detection (brand parsers)
-        let device = self.detect_device(ua);
+        let (device, device_rule) = self.detect_device(ua).unzip();
+        rule_ids.device = device_rule.flatten();
+        let mut partial = Detection {
+            bot,
+            os,
+            client,
+            device,
+            android_webview: false,
+            emulator: false,
+            rule_ids,
+        };
+        self.run_stages(PipelineStage::Device, ua, hints, &mut partial);
+        let Detection {
+            bot,
+            os,
+            client,
+            device,
+            mut rule_ids,
+            ..
+        } = partial;
 
         // Decompose device into its parts so we can merge results from
         // multiple heuristic steps (vendor fragments, Apple inference, desktop
@@ -427,6 +991,19 @@ impl DeviceDetector {
             Some(d) => (d.kind, d.brand, d.model),
             None => (None, Cow::Borrowed(""), Cow::Borrowed("")),
         };
+        // Fire TV sticks share the Amazon brand gate with Fire tablets, whose
+        // brand-level type is tablet.  An AFT* code without a model entry of
+        // its own is still a TV.
+        if brand == "Amazon"
+            && rule_ids.device.is_some_and(|r| r.model.is_none())
+            && self.heuristic_regexes.fire_tv.is_match(ua).unwrap_or(false)
+        {
+            device_type = Some(DeviceType::Tv);
+        }
+
+        // Type claimed by the device data itself, as opposed to the UA
+        // heuristics below.
+        let parser_device_type = device_type;
 
         // Matomo treats the "Unknown" brand as empty (AbstractDeviceParser.php:2390).
         if brand == "Unknown" {
@@ -437,26 +1014,89 @@ impl DeviceDetector {
         if brand.is_empty() {
             if let Some(m) = self.vendor_fragment_parser.match_first(ua) {
                 brand = Cow::Borrowed(m.data.brand.as_str());
+                rule_ids.device = Some(RuleId {
+                    file: "vendorfragments.yml",
+                    index: m.index,
+                    model: None,
+                });
+            }
+        }
+
+        // A brand without a model on Android: fall back to the `; MODEL Build/`
+        // token, like Matomo's generic model.
+        if model.is_empty()
+            && !brand.is_empty()
+            && os.as_ref().is_some_and(|o| is_android_os(&o.name))
+        {
+            if let Some(build_model) = android_build_model(ua) {
+                model = Cow::Borrowed(build_model);
+            }
+        }
+
+        // Client hints: a `Sec-CH-UA-Model` value such as "Pixel 7" implies its
+        // brand.  Run it through the device parsers as a synthetic Android UA
+        // (Matomo substitutes the model into the reduced `Android 10; K` UA).
+        if brand.is_empty() {
+            if let Some(hint_model) = hints.and_then(|h| h.model.as_deref()) {
+                if let Some(hint_brand) = self.brand_from_model_hint(hint_model) {
+                    brand = hint_brand;
+                }
+            }
+        }
+
+        // E-readers: Kindle and Kobo firmware browsers claim Linux/Android
+        // without a model token.  Matomo reports them as tablets.
+        if brand.is_empty() {
+            let hr = &self.heuristic_regexes;
+            if hr.kindle.is_match(ua).unwrap_or(false) {
+                brand = Cow::Borrowed("Amazon");
+                model = Cow::Borrowed("Kindle");
+                device_type = Some(DeviceType::Tablet);
+            } else if hr.kobo.is_match(ua).unwrap_or(false) {
+                brand = Cow::Borrowed("Rakuten Kobo");
+                device_type = Some(DeviceType::Tablet);
             }
         }
 
         // 7. Apple brand heuristics (Matomo DeviceDetector.php:920-934).
         let os_name = os.as_ref().map(|o| o.name.as_ref()).unwrap_or("");
         let os_version = os.as_ref().map(|o| o.version.as_ref()).unwrap_or("");
-        let is_apple_os = matches!(os_name, "iPadOS" | "tvOS" | "watchOS" | "iOS" | "Mac");
+        let is_apple_os = is_apple_os(os_name);
         let is_android_family = os.as_ref().map_or(false, |o| is_android_os(&o.name));
         let client_name = client.as_ref().map(|c| c.name.as_ref()).unwrap_or("");
 
-        if brand == "Apple" && !is_apple_os {
+        // A Mac token pasted into a Windows or Android UA can win the OS
+        // match; the real platform token next to it still gives it away.
+        let spoofed_apple_os = is_apple_os
+            && self
+                .heuristic_regexes
+                .non_apple_os
+                .is_match(ua)
+                .unwrap_or(false);
+
+        // HomePods run audioOS, a tvOS derivative whose UAs may carry no
+        // platform token the OS parser knows; the marker alone settles it.
+        let homepod = self.heuristic_regexes.homepod.is_match(ua).unwrap_or(false);
+
+        if brand == "Apple" && (!is_apple_os || spoofed_apple_os) && !homepod {
             device_type = None;
             brand = Cow::Borrowed("");
             model = Cow::Borrowed("");
+            rule_ids.device = None;
         }
 
-        if brand.is_empty() && is_apple_os {
+        if brand.is_empty() && is_apple_os && !spoofed_apple_os {
             brand = Cow::Borrowed("Apple");
         }
 
+        if homepod {
+            brand = Cow::Borrowed("Apple");
+            device_type = Some(DeviceType::SmartSpeaker);
+            if model.is_empty() {
+                model = Cow::Borrowed("HomePod");
+            }
+        }
+
         // --- Device-type heuristics (Matomo DeviceDetector.php:936-1128) ---
 
         let hr = &self.heuristic_regexes;
@@ -478,6 +1118,18 @@ impl DeviceDetector {
             }
         }
 
+        // Firefox on Android carries its own "Mobile"/"Tablet" token
+        if device_type.is_none()
+            && is_android_family
+            && hr.firefox_android.is_match(ua).unwrap_or(false)
+        {
+            if hr.mobile_elibom.is_match(ua).unwrap_or(false) {
+                device_type = Some(DeviceType::Smartphone);
+            } else if hr.tablet_token.is_match(ua).unwrap_or(false) {
+                device_type = Some(DeviceType::Tablet);
+            }
+        }
+
         // Pad/APad → tablet
         if device_type == Some(DeviceType::Smartphone)
             && hr.pad_apad.is_match(ua).unwrap_or(false)
@@ -500,6 +1152,16 @@ impl DeviceDetector {
             device_type = Some(DeviceType::Smartphone);
         }
 
+        // Phone-first Linux systems (Sailfish OS, Ubuntu Touch, ...) are
+        // smartphones unless they say tablet.
+        if device_type.is_none() && is_mobile_linux(os_name) {
+            device_type = if hr.tablet_token.is_match(ua).unwrap_or(false) {
+                Some(DeviceType::Tablet)
+            } else {
+                Some(DeviceType::Smartphone)
+            };
+        }
+
         // Android version heuristics
         if device_type.is_none() && os_name == "Android" && !os_version.is_empty() {
             if version_lt(os_version, "2.0") {
@@ -509,8 +1171,12 @@ impl DeviceDetector {
             }
         }
 
-        // Feature phone on Android → smartphone
-        if device_type == Some(DeviceType::FeaturePhone) && is_android_family {
+        // Feature phone on a smartphone OS (Android, iOS, mobile Linux, ...)
+        // → smartphone
+        if device_type == Some(DeviceType::FeaturePhone)
+            && is_mobile_os(os_name)
+            && !is_feature_phone_os(os_name)
+        {
             device_type = Some(DeviceType::Smartphone);
         }
 
@@ -524,6 +1190,16 @@ impl DeviceDetector {
             device_type = Some(DeviceType::FeaturePhone);
         }
 
+        // Proxy browsers for low-end handsets render pages server-side; with
+        // no smartphone OS in sight the handset is a feature phone, whatever
+        // type the device data gave its brand.
+        if matches!(client_name, "Opera Mini" | "UC Browser Mini")
+            && (os_name.is_empty() || is_feature_phone_os(os_name))
+            && matches!(device_type, None | Some(DeviceType::Smartphone))
+        {
+            device_type = Some(DeviceType::FeaturePhone);
+        }
+
         // Windows 8+ touch → tablet
         if device_type.is_none()
             && (os_name == "Windows RT"
@@ -564,29 +1240,38 @@ impl DeviceDetector {
             device_type = Some(DeviceType::Tv);
         }
 
+        // Android set-top/IPTV boxes: Xiaomi's MiBOX/MiTV and the generic
+        // Amlogic boxes (X96, H96, T95, TX3, MXQ, "AOSP on p2xx") → tv
+        let mi_set_top = hr.mi_set_top.is_match(ua).unwrap_or(false);
+        if !matches!(
+            device_type,
+            Some(DeviceType::Tv) | Some(DeviceType::Peripheral)
+        ) && (mi_set_top || hr.set_top_box.is_match(ua).unwrap_or(false))
+        {
+            device_type = Some(DeviceType::Tv);
+        }
+        if mi_set_top && brand.is_empty() {
+            brand = Cow::Borrowed("Xiaomi");
+        }
+
         // Tizen TV / SmartTV → tv
         if device_type.is_none() && hr.smart_tv_tizen.is_match(ua).unwrap_or(false) {
             device_type = Some(DeviceType::Tv);
         }
 
+        // Tizen is Samsung's.  Only TV-marked Tizen UAs (above) are TVs; bare
+        // Tizen UAs with a "Mobile" token are the Z-series phones.
+        if os_name == "Tizen" {
+            if brand.is_empty() {
+                brand = Cow::Borrowed("Samsung");
+            }
+            if device_type.is_none() && hr.mobile_elibom.is_match(ua).unwrap_or(false) {
+                device_type = Some(DeviceType::Smartphone);
+            }
+        }
+
         // Known TV client names → tv
-        if matches!(
-            client_name,
-            "Kylo"
-                | "Espial TV Browser"
-                | "LUJO TV Browser"
-                | "LogicUI TV Browser"
-                | "Open TV Browser"
-                | "Seraphic Sraf"
-                | "Opera Devices"
-                | "Crow Browser"
-                | "Vewd Browser"
-                | "TiviMate"
-                | "Quick Search TV"
-                | "QJY TV Browser"
-                | "TV Bro"
-                | "Redline"
-        ) {
+        if self.tv_clients.contains(client_name) {
             device_type = Some(DeviceType::Tv);
         }
 
@@ -595,14 +1280,87 @@ impl DeviceDetector {
             device_type = Some(DeviceType::Tv);
         }
 
-        // "Desktop" fragment → desktop
+        // Cast (CrKey) firmware is shared by Nest displays and speakers; only
+        // a screen token tells them apart.  Runs before desktop-OS inference
+        // since these UAs claim Linux/Fuchsia.
+        if ua.contains("CrKey") {
+            let cast_type = if hr.cast_display.is_match(ua).unwrap_or(false) {
+                Some(DeviceType::SmartDisplay)
+            } else if hr.cast_speaker.is_match(ua).unwrap_or(false) {
+                Some(DeviceType::SmartSpeaker)
+            } else {
+                None
+            };
+            if cast_type.is_some() {
+                device_type = cast_type;
+                if brand.is_empty() {
+                    brand = Cow::Borrowed("Google");
+                }
+            }
+        }
+
+        // Fire tablets and Lenovo Smart Tabs docked in Show Mode act as the
+        // assistant's smart display.
+        if device_type == Some(DeviceType::Tablet)
+            && matches!(brand.as_ref(), "Amazon" | "Lenovo")
+            && hr.show_mode.is_match(ua).unwrap_or(false)
+        {
+            device_type = Some(DeviceType::SmartDisplay);
+        }
+
+        // `Sec-CH-UA-Mobile: ?0` outranks the UA heuristics above (e.g. an
+        // Android device in desktop mode); leave the type to desktop-OS
+        // inference.  A device-parser match is data-driven and stands.
+        let hinted_mobile = hints.and_then(|h| h.mobile) == Some(true);
+        let hinted_not_mobile = hints.and_then(|h| h.mobile) == Some(false);
+        if hinted_not_mobile
+            && parser_device_type.is_none()
+            && matches!(
+                device_type,
+                Some(DeviceType::Smartphone) | Some(DeviceType::Tablet)
+            )
+        {
+            device_type = None;
+        }
+
+        // Android-x86 and BlueStacks run Android on a PC; the phone model
+        // they report is usually spoofed, so the device is a desktop whatever
+        // the device files or Android heuristics said, with the phone's
+        // brand and model dropped.
+        let emulator = hr.android_emulator.is_match(ua).unwrap_or(false);
+        if emulator && !hinted_mobile {
+            device_type = Some(DeviceType::Desktop);
+            brand = Cow::Borrowed("");
+            model = Cow::Borrowed("");
+            rule_ids.device = None;
+        }
+
+        // "Desktop" fragment → desktop, unless client hints say the device
+        // is mobile (e.g. a phone requesting the desktop site).
         if device_type != Some(DeviceType::Desktop)
+            && !hinted_mobile
             && ua.contains("Desktop")
             && hr.desktop_fragment.is_match(ua).unwrap_or(false)
         {
             device_type = Some(DeviceType::Desktop);
         }
 
+        // Chrome OS tablets, and convertibles in tablet mode, send a touch or
+        // tablet token or a `Tablet` form-factor hint; the rest are laptops
+        // and fall through to desktop-OS inference.
+        if device_type.is_none()
+            && os_family(os_name) == Some("Chrome OS")
+            && (hr.touch.is_match(ua).unwrap_or(false)
+                || hr.tablet_token.is_match(ua).unwrap_or(false)
+                || hints.is_some_and(|h| {
+                    h.form_factors
+                        .iter()
+                        .any(|f| f.trim_matches('"').eq_ignore_ascii_case("Tablet"))
+                }))
+        {
+            device_type = Some(DeviceType::Tablet);
+        }
+
         // Desktop OS inference (Matomo DeviceDetector.php:1123-1128).
         if device_type.is_none() {
             if os.as_ref().map_or(false, |o| is_desktop_os(&o.name)) {
@@ -610,20 +1368,58 @@ impl DeviceDetector {
             }
         }
 
+        // Opt-in: laptops are notebooks, whether matched by notebooks.yml or
+        // recognised by a laptop token on a desktop OS.
+        let notebooks = DeviceFile::Notebooks.file_name();
+        if self.prefer_notebook_type
+            && device_type == Some(DeviceType::Desktop)
+            && (rule_ids.device.is_some_and(|r| r.file == notebooks)
+                || (is_desktop_os(os_name) && hr.laptop.is_match(ua).unwrap_or(false)))
+        {
+            device_type = Some(DeviceType::Notebook);
+        }
+
         // --- Client hints: device model fallback ---
-        if model.is_empty() {
+        // Desktop Chromium sends an empty model; a non-empty one on a desktop
+        // UA comes from a misconfigured client unless it also claims mobile.
+        let desktop_type = matches!(
+            device_type,
+            Some(DeviceType::Desktop | DeviceType::Notebook)
+        );
+        if model.is_empty() && (!desktop_type || hinted_mobile) {
             if let Some(hint_model) = hints.and_then(|h| h.model.as_deref()) {
                 if !hint_model.is_empty() {
-                    model = Cow::Owned(hint_model.to_string());
+                    model = owned(hint_model.to_string());
                 }
             }
         }
 
+        // Apple Silicon Macs still claim "Intel Mac OS X"; only the
+        // architecture hint reveals them.
+        if os_name == "Mac"
+            && os.as_ref().is_some_and(|o| o.platform == "ARM")
+            && device_type == Some(DeviceType::Desktop)
+            && model.is_empty()
+        {
+            model = Cow::Borrowed("Mac");
+        }
+
         // --- Client hints: mobile flag ---
-        if device_type.is_none() {
-            if hints.and_then(|h| h.mobile) == Some(true) {
-                device_type = Some(DeviceType::Smartphone);
-            }
+        if device_type.is_none() && hinted_mobile {
+            device_type = Some(DeviceType::Smartphone);
+        }
+
+        // Opt-in consistency pass: an Apple OS implies an Apple device, so a
+        // foreign brand (from a stray model token or hint) is replaced.
+        if self.reset_inconsistent_brand
+            && is_apple_os
+            && !spoofed_apple_os
+            && !brand.is_empty()
+            && brand != "Apple"
+        {
+            brand = Cow::Borrowed("Apple");
+            model = Cow::Borrowed("");
+            rule_ids.device = None;
         }
 
         // Build final device if we determined a type or a brand.
@@ -637,45 +1433,261 @@ impl DeviceDetector {
             None
         };
 
-        Detection {
-            bot: None,
+        // Android WebView: the `wv` token inside the platform parentheses.
+        let android_webview = is_android_family && ua.contains("; wv)");
+
+        let mut detection = Detection {
+            bot,
             os,
             client,
             device,
+            android_webview,
+            emulator,
+            rule_ids,
+        };
+        self.run_stages(PipelineStage::Heuristics, ua, hints, &mut detection);
+        detection
+    }
+
+    /// Run the custom stages registered after `step` over the partial
+    /// detection.
+    fn run_stages<'a>(
+        &self,
+        step: PipelineStage,
+        ua: &'a str,
+        hints: Option<&ClientHints>,
+        detection: &mut Detection<'a>,
+    ) {
+        for Stage(stage) in &self.stages {
+            if stage.after() == step {
+                stage.run(ua, hints, detection);
+            }
         }
     }
 
-    fn detect_client<'a>(&'a self, ua: &'a str) -> Option<Client<'a>> {
-        let parsers: &[(&CompiledParser<ClientData>, ClientType)] = &[
-            (&self.browser_parser, ClientType::Browser),
-            (&self.feed_reader_parser, ClientType::FeedReader),
-            (&self.mobile_app_parser, ClientType::MobileApp),
-            (&self.library_parser, ClientType::Library),
-            (&self.media_player_parser, ClientType::MediaPlayer),
-            (&self.pim_parser, ClientType::Pim),
-        ];
+    /// Entry counts across all parsers, including how many PCRE-only entries
+    /// were dropped by [`standard_regex_only`](DeviceDetectorBuilder::standard_regex_only).
+    pub fn stats(&self) -> DetectorStats {
+        let mut stats = DetectorStats::default();
+        stats += self.bot_parser.stats();
+        stats += self.os_parser.stats();
+        for (parser, _) in self.client_parsers() {
+            stats += parser.stats();
+        }
+        stats += self.engine_parser.stats();
+        stats += self.vendor_fragment_parser.stats();
+        for (_, _, _, _, parser) in &self.device_parsers {
+            stats += parser.stats();
+        }
+        stats
+    }
+
+    /// Brand names known to each device file, keyed by the file's default
+    /// device type (e.g. `Console` for `consoles.yml`).  Files sharing a type
+    /// (`shell_tv.yml` and `televisions.yml`) are merged, keeping first
+    /// occurrence order.
+    pub fn brands_by_type(&self) -> HashMap<DeviceType, Vec<&str>> {
+        let mut by_type: HashMap<DeviceType, Vec<&str>> = HashMap::new();
+        for (_, device_type, _, _, parser) in &self.device_parsers {
+            let brands = by_type.entry(*device_type).or_default();
+            for data in parser.brands() {
+                if !brands.contains(&&*data.brand) {
+                    brands.push(&data.brand);
+                }
+            }
+        }
+        by_type
+    }
+
+    /// OS names oss.yml can produce, in file order without duplicates.
+    /// Templated names (containing `$`) depend on the UA and are left out.
+    pub fn known_os_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for data in self.os_parser.entries() {
+            if !data.name.contains('$') && !names.contains(&data.name.as_str()) {
+                names.push(&data.name);
+            }
+        }
+        names
+    }
 
-        for (parser, _default_kind) in parsers {
+    /// Report the source regex that matched at each detection stage.
+    ///
+    /// Stages are evaluated independently of one another (a bot match does
+    /// not stop the others), and heuristics applied by
+    /// [`parse_with_hints`](Self::parse_with_hints) are not reflected.
+    pub fn explain<'a>(&'a self, ua: &'a str) -> Explanation<'a> {
+        let mut explanation = Explanation {
+            bot: self.bot_parser.match_first(ua).map(|m| m.pattern),
+            os: self.os_parser.match_first(ua).map(|m| m.pattern),
+            client: self
+                .client_parsers()
+                .iter()
+                .find_map(|(parser, _)| parser.match_first(ua))
+                .map(|m| m.pattern),
+            ..Default::default()
+        };
+
+        for (_, _, prefilter, _, parser) in &self.device_parsers {
+            if !prefilter.matches(ua) {
+                continue;
+            }
             if let Some(m) = parser.match_first(ua) {
-                let version = match &m.data.version_template {
-                    Some(tpl) => substitute(tpl, &m.captures),
-                    None => capture_or_empty(&m.captures, 1),
-                };
+                explanation.device_brand = Some(m.brand_pattern);
+                explanation.device_model = m.model_match.map(|mm| mm.pattern);
+                break;
+            }
+        }
 
-                // Resolve engine: use default from browser entry, or fall back to engine parser.
-                let (engine, engine_version) = self.resolve_engine(ua, m.data, &version);
+        explanation
+    }
 
-                return Some(Client {
-                    kind: m.data.kind,
-                    name: substitute(&m.data.name, &m.captures),
-                    version,
-                    engine,
-                    engine_version,
-                });
+    /// The client hints that changed the detection of `ua`: for each
+    /// populated header, the difference between parsing with all of `hints`
+    /// and parsing without that header.  Headers with no effect are left out.
+    ///
+    /// Diagnostic only: it re-parses once per header.  Pair it with
+    /// [`ClientHints::debug_summary`] to see what was parsed in the first place.
+    pub fn hint_influence(
+        &self,
+        ua: &str,
+        hints: &ClientHints,
+    ) -> Vec<(&'static str, DetectionDiff)> {
+        let with_hints = self.parse_with_hints(ua, Some(hints));
+        hints
+            .populated()
+            .filter_map(|(header, _)| {
+                let without = self.parse_with_hints(ua, Some(&hints.without(header)));
+                let diff = DetectionDiff::compare(&without, &with_hints);
+                (!diff.is_empty()).then_some((header, diff))
+            })
+            .collect()
+    }
+
+    /// Entries of the data file `stage` (a [`RuleId::file`] name such as
+    /// `browsers.yml` or `mobiles.yml`) whose literal appears in `ua` but
+    /// whose regex does not match, as `(entry index, source pattern)`.
+    ///
+    /// Diagnostic only: a near miss usually points at a boundary or escaping
+    /// problem in the dataset.  Only entries that go through the literal
+    /// prefilter are considered (fancy_regex patterns bypass it), device
+    /// files report brand gates rather than models, and an unknown file
+    /// name yields an empty list.
+    pub fn near_misses<'a>(&'a self, ua: &str, stage: &str) -> Vec<(usize, &'a str)> {
+        match stage {
+            "bots.yml" => self.bot_parser.near_misses(ua),
+            "oss.yml" => self.os_parser.near_misses(ua),
+            "browser_engine.yml" => self.engine_parser.near_misses(ua),
+            "vendorfragments.yml" => self.vendor_fragment_parser.near_misses(ua),
+            _ => {
+                if let Some((parser, _)) = self
+                    .client_parsers()
+                    .into_iter()
+                    .find(|(_, file)| *file == stage)
+                {
+                    return parser.near_misses(ua);
+                }
+                self.device_parsers
+                    .iter()
+                    .find(|(file, ..)| *file == stage)
+                    .map(|(.., parser)| parser.near_misses(ua))
+                    .unwrap_or_default()
             }
         }
+    }
+
+    /// Client parsers in detection order.
+    fn client_parsers(&self) -> [(&CompiledParser<ClientData>, &'static str); 8] {
+        // Extension runtimes ride on top of a regular browser UA, so they are
+        // checked first to avoid being shadowed by the host browser.
+        [
+            (&self.extension_parser, "<builtin:browser_extensions>"),
+            (&self.browser_parser, "browsers.yml"),
+            (&self.feed_reader_parser, "feed_readers.yml"),
+            (&self.mobile_app_parser, "mobile_apps.yml"),
+            (&self.library_parser, "libraries.yml"),
+            (&self.library_supplement_parser, "<builtin:http_libraries>"),
+            (&self.media_player_parser, "mediaplayers.yml"),
+            (&self.pim_parser, "pim.yml"),
+        ]
+    }
 
-        None
+    /// Every bots.yml entry matching `ua` with its entry index, lowest index
+    /// (highest priority) first.  [`parse`](Self::parse) reports only the
+    /// first; more than one hit means the UA is ambiguous between bots.
+    pub fn rank_bots<'a>(&'a self, ua: &'a str) -> Vec<(Bot<'a>, usize)> {
+        self.bot_parser
+            .match_all(ua)
+            .into_iter()
+            .map(|m| {
+                let index = m.index;
+                (bot_from_match(m), index)
+            })
+            .collect()
+    }
+
+    /// Every client parser's first match for `ua`, in detection priority
+    /// order, without stopping at the first parser that fires.
+    ///
+    /// Diagnostic only: [`parse`](Self::parse) keeps just the first entry, and
+    /// no client-hint (`X-Requested-With`) override is applied here.
+    pub fn all_client_matches<'a>(&'a self, ua: &'a str) -> Vec<Client<'a>> {
+        self.client_parsers()
+            .into_iter()
+            .filter_map(|(parser, _)| parser.match_first(ua))
+            .map(|m| self.client_from_match(ua, m))
+            .collect()
+    }
+
+    /// Every browser_engine.yml entry matching `ua` as `(name, version)`, in
+    /// dataset order.
+    ///
+    /// Diagnostic only: UAs routinely claim several engines (Chrome carries
+    /// `AppleWebKit` and `like Gecko`), and the client's engine is picked
+    /// from its browser entry, not from this list.
+    pub fn all_engines(&self, ua: &str) -> Vec<(String, String)> {
+        self.engine_parser
+            .match_all(ua)
+            .into_iter()
+            .map(|m| {
+                let name = m.data.name.as_str();
+                (name.to_string(), self.engine_version(ua, name).into_owned())
+            })
+            .collect()
+    }
+
+    fn detect_client<'a>(&'a self, ua: &'a str) -> Option<(Client<'a>, RuleId)> {
+        self.client_parsers()
+            .into_iter()
+            .find_map(|(parser, file)| parser.match_first(ua).map(|m| (m, file)))
+            .map(|(m, file)| {
+                let rule = RuleId {
+                    file,
+                    index: m.index,
+                    model: None,
+                };
+                (self.client_from_match(ua, m), rule)
+            })
+    }
+
+    fn client_from_match<'a>(&'a self, ua: &'a str, m: MatchResult<'a, ClientData>) -> Client<'a> {
+        let version = match &m.data.version_template {
+            Some(tpl) => substitute(tpl, &m.captures),
+            None => first_numeric_capture(&m.captures),
+        };
+
+        // Resolve engine: use default from browser entry, or fall back to engine parser.
+        let (engine, engine_version) = self.resolve_engine(ua, m.data, &version);
+
+        Client {
+            kind: m.data.kind,
+            name: substitute(&m.data.name, &m.captures),
+            version,
+            engine,
+            engine_version,
+            mode: None,
+            channel: None,
+        }
     }
 
     fn resolve_engine<'a>(
@@ -684,106 +1696,274 @@ impl DeviceDetector {
         client_data: &'a ClientData,
         browser_version: &str,
     ) -> (Cow<'a, str>, Cow<'a, str>) {
-        if let Some(default_engine) = &client_data.engine_default {
-            // Determine the engine name: start with the default, then apply
-            // version-threshold overrides (last threshold where browser_version
-            // >= threshold wins).
-            let mut engine_name: &str = default_engine;
-            if !browser_version.is_empty() {
-                if let Some(ref versions) = client_data.engine_versions {
-                    for (threshold, name) in versions {
-                        if version_ge(browser_version, threshold) {
-                            engine_name = name;
+        // The browser entry only decides the engine *name*: its default, then
+        // version-threshold overrides (last threshold where browser_version
+        // >= threshold wins).  Without a default, the engine parser names it.
+        let engine_name: &'a str = match &client_data.engine_default {
+            Some(default_engine) => {
+                let mut engine_name: &str = default_engine;
+                if !browser_version.is_empty() {
+                    if let Some(ref versions) = client_data.engine_versions {
+                        for (threshold, name) in versions {
+                            if version_ge(browser_version, threshold) {
+                                engine_name = name;
+                            }
                         }
                     }
                 }
+                engine_name
             }
+            None => match self.engine_parser.match_first(ua) {
+                Some(m) => m.data.name.as_str(),
+                None => "",
+            },
+        };
+        if engine_name.is_empty() {
+            return (Cow::Borrowed(""), Cow::Borrowed(""));
+        }
 
-            if !engine_name.is_empty() {
-                // Try engine parser to get the engine version from the UA.
-                if let Some(m) = self.engine_parser.match_first(ua) {
-                    if m.data.name.eq_ignore_ascii_case(engine_name) {
-                        return (
-                            Cow::Borrowed(m.data.name.as_str()),
-                            capture_or_empty(&m.captures, 1),
-                        );
-                    }
+        (
+            Cow::Borrowed(engine_name),
+            self.engine_version(ua, engine_name),
+        )
+    }
+
+    /// The package ID to look up for an `X-Requested-With` value.  Proxies
+    /// sometimes forward a comma-separated list or append extra tokens, so
+    /// when the value as a whole is not a known package, its first comma- or
+    /// whitespace-separated token that is one wins, else its first token.
+    fn xrw_package<'h>(&self, xrw: &'h str) -> &'h str {
+        let is_known =
+            |p: &str| self.app_hints.contains_key(p) || self.browser_hints.contains_key(p);
+        if is_known(xrw) {
+            return xrw;
+        }
+        let mut tokens = xrw
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|t| !t.is_empty());
+        let first = tokens.clone().next().unwrap_or(xrw);
+        tokens.find(|t| is_known(t)).unwrap_or(first)
+    }
+
+    fn client_mode(&self, ua: &str) -> Option<&'static str> {
+        let hr = &self.heuristic_regexes;
+        if hr.reader_mode.is_match(ua).unwrap_or(false) {
+            Some("reader")
+        } else if hr.private_mode.is_match(ua).unwrap_or(false) {
+            Some("private")
+        } else if hr.compat_view.is_match(ua).unwrap_or(false) {
+            // IE 8-11 (and Edge's IE mode) in compatibility view claim
+            // MSIE 7.0 but keep their real Trident token.
+            Some("compatibility")
+        } else {
+            None
+        }
+    }
+
+    /// Pre-release channel named by a UA token: Firefox's `a1`/`a2`/`bN`
+    /// version suffixes, or a `Canary`/`Nightly`/`Dev`/`Beta` product token.
+    fn client_channel(&self, ua: &str) -> Option<&'static str> {
+        let hr = &self.heuristic_regexes;
+        [
+            (&hr.channel_canary, "canary"),
+            (&hr.channel_nightly, "nightly"),
+            (&hr.channel_dev, "dev"),
+            (&hr.channel_beta, "beta"),
+        ]
+        .into_iter()
+        .find(|(re, _)| re.is_match(ua).unwrap_or(false))
+        .map(|(_, channel)| channel)
+    }
+
+    /// The engine's own version as found in the UA (e.g. Gecko's `rv:`),
+    /// never derived from the browser version.
+    fn engine_version<'a>(&'a self, ua: &'a str, engine_name: &str) -> Cow<'a, str> {
+        let extractors = self.engine_version_regexes.get(engine_name);
+        for re in extractors.into_iter().flatten() {
+            if let Ok(Some(caps)) = re.captures(ua) {
+                if let Some(version) = caps.get(1) {
+                    return Cow::Borrowed(version.as_str());
                 }
-                return (Cow::Borrowed(engine_name), Cow::Borrowed(""));
             }
         }
 
-        // No default engine → try engine parser directly
-        if let Some(m) = self.engine_parser.match_first(ua) {
-            return (
-                Cow::Borrowed(m.data.name.as_str()),
-                capture_or_empty(&m.captures, 1),
-            );
+        // Fall back to a capture in the engine's own browser_engine.yml entry.
+        match self.engine_parser.match_first(ua) {
+            Some(m) if m.data.name.eq_ignore_ascii_case(engine_name) => {
+                capture_or_empty(&m.captures, 1)
+            }
+            _ => Cow::Borrowed(""),
         }
-
-        (Cow::Borrowed(""), Cow::Borrowed(""))
     }
 
-    fn detect_device<'a>(&'a self, ua: &'a str) -> Option<Device<'a>> {
-        for (default_type, prefilter, claims_type, parser) in &self.device_parsers {
+    /// The matched device plus the brand/model entry behind it (`None` for a
+    /// typeless device claimed by a prefilter alone).
+    fn detect_device<'a>(&'a self, ua: &'a str) -> Option<(Device<'a>, Option<RuleId>)> {
+        // Best candidate so far under `MostSpecific`, with its score: 0 for a
+        // brand-only match, else 1 + the length of the matched model text.
+        let mut best: Option<((Device<'a>, Option<RuleId>), usize)> = None;
+        // Default type of the first file whose prefilter matched without a
+        // brand, under `prefilter_implies_type`.
+        let mut weak_type = None;
+
+        for (file, default_type, prefilter, claims_type, parser) in &self.device_parsers {
             if !prefilter.matches(ua) {
                 continue;
             }
-            
+
             if let Some(m) = parser.match_first(ua) {
-                let brand_data = m.brand_data;
-
-                if let Some(model_match) = &m.model_match {
-                    // Model regex matched — use model data, falling back to brand data.
-                    let device_type = model_match
-                        .data
-                        .device_type
-                        .or(brand_data.device_type)
-                        .unwrap_or(*default_type);
-                    let brand = model_match
-                        .data
-                        .brand
-                        .as_deref()
-                        .unwrap_or(&brand_data.brand);
-                    let model = match &model_match.data.model_template {
-                        Some(tpl) => substitute(tpl, &model_match.captures),
-                        None => Cow::Borrowed(""),
-                    };
-
-                    return Some(Device {
-                        kind: Some(device_type),
-                        brand: Cow::Borrowed(brand),
-                        model,
-                    });
-                } else {
-                    // Only brand regex matched, no specific model.
-                    let device_type = brand_data.device_type.unwrap_or(*default_type);
-                    let model = match &brand_data.model_template {
-                        Some(tpl) => substitute(tpl, &m.brand_captures),
-                        None => Cow::Borrowed(""),
-                    };
-
-                    return Some(Device {
-                        kind: Some(device_type),
-                        brand: Cow::Borrowed(&brand_data.brand),
-                        model,
-                    });
+                let score = m
+                    .model_match
+                    .as_ref()
+                    .map_or(0, |mm| 1 + mm.captures.get_str(0).map_or(0, str::len));
+                let found = device_from_match(file, *default_type, m);
+                if self.device_selection == DeviceSelection::FirstMatch {
+                    return Some(found);
+                }
+                if best.as_ref().is_none_or(|(_, s)| score > *s) {
+                    best = Some((found, score));
                 }
+                continue;
             }
 
             // Prefilter matched but no brand matched.  For parsers that
             // "claim" the device type (HbbTv, ShellTv), return a typeless
             // device to prevent later parsers from producing false positives.
             if *claims_type {
-                return Some(Device {
-                    kind: Some(*default_type),
-                    brand: Cow::Borrowed(""),
-                    model: Cow::Borrowed(""),
-                });
+                if let Some((found, _)) = best {
+                    return Some(found);
+                }
+                return Some((
+                    Device {
+                        kind: Some(*default_type),
+                        brand: Cow::Borrowed(""),
+                        model: Cow::Borrowed(""),
+                    },
+                    None,
+                ));
+            }
+
+            if self.prefilter_implies_type
+                && weak_type.is_none()
+                && !matches!(prefilter, DevicePrefilter::None)
+            {
+                weak_type = Some(*default_type);
+            }
+        }
+
+        best.map(|(found, _)| found).or_else(|| {
+            let kind = weak_type?;
+            let device = Device {
+                kind: Some(kind),
+                brand: Cow::Borrowed(""),
+                model: Cow::Borrowed(""),
+            };
+            Some((device, None))
+        })
+    }
+
+    /// Let `Sec-CH-UA-Platform` override the UA-derived OS.
+    ///
+    /// A hint in the same family as the UA OS keeps the UA's more specific
+    /// name (e.g. "Ubuntu" over "GNU/Linux") and falls back to the UA version
+    /// when the hint has none.  An empty or "Unknown" platform is ignored.
+    fn apply_platform_hint<'a>(
+        &self,
+        os: Option<Os<'a>>,
+        ua: &str,
+        hints: Option<&ClientHints>,
+    ) -> Option<Os<'a>> {
+        let Some(hint_name) = hints
+            .and_then(|h| h.platform.as_deref())
+            .and_then(os_name_from_platform_hint)
+        else {
+            return os;
+        };
+        let hint_version = os_version_from_platform_hint(
+            hint_name,
+            hints
+                .and_then(|h| h.platform_version.as_deref())
+                .unwrap_or(""),
+        );
+
+        match os {
+            Some(mut ua_os)
+                if ua_os.name.eq_ignore_ascii_case(hint_name)
+                    || (os_family(&ua_os.name).is_some()
+                        && os_family(&ua_os.name) == os_family(hint_name)) =>
+            {
+                if !hint_version.is_empty() {
+                    ua_os.version = owned(hint_version);
+                }
+                Some(ua_os)
+            }
+            _ => Some(Os {
+                name: owned(hint_name.to_string()),
+                version: owned(hint_version),
+                platform: Cow::Borrowed(self.detect_platform(ua, hints)),
+                version_inferred: false,
+            }),
+        }
+    }
+
+    /// CPU platform, preferring the `Sec-CH-UA-Arch` hint over UA tokens
+    /// (Matomo's `OperatingSystem::parsePlatform`).
+    fn detect_platform(&self, ua: &str, hints: Option<&ClientHints>) -> &'static str {
+        if let Some(arch) = hints.and_then(|h| h.architecture.as_deref()) {
+            let arch = arch.trim_matches('"').to_ascii_lowercase();
+            let bitness = hints
+                .and_then(|h| h.bitness.as_deref())
+                .map(|b| b.trim_matches('"'));
+            if arch.contains("arm") {
+                return "ARM";
+            }
+            if arch.contains("loongarch64") {
+                return "LoongArch64";
+            }
+            if arch.contains("mips") {
+                return "MIPS";
+            }
+            if arch.contains("sh4") {
+                return "SuperH";
+            }
+            if arch.contains("sparc64") {
+                return "SPARC64";
+            }
+            if arch.contains("x64") || (arch.contains("x86") && bitness == Some("64")) {
+                return "x64";
+            }
+            if arch.contains("x86") {
+                return "x86";
             }
         }
 
-        None
+        let hr = &self.heuristic_regexes;
+        let platforms = [
+            (&hr.platform_arm, "ARM"),
+            (&hr.platform_loongarch, "LoongArch64"),
+            (&hr.platform_mips, "MIPS"),
+            (&hr.platform_superh, "SuperH"),
+            (&hr.platform_sparc, "SPARC64"),
+            (&hr.platform_x64, "x64"),
+            (&hr.platform_x86, "x86"),
+        ];
+        platforms
+            .into_iter()
+            .find(|(re, _)| re.is_match(ua).unwrap_or(false))
+            .map_or("", |(_, name)| name)
+    }
+
+    /// Resolve the brand implied by a `Sec-CH-UA-Model` hint, if any.
+    fn brand_from_model_hint<'a>(&'a self, hint_model: &str) -> Option<Cow<'a, str>> {
+        if hint_model.is_empty() {
+            return None;
+        }
+        let probe = format!("Android 10; {} Build/", hint_model);
+        let (device, _) = self.detect_device(&probe)?;
+        if device.brand.is_empty() || device.brand == "Unknown" {
+            return None;
+        }
+        Some(owned(device.brand.into_owned()))
     }
 }
 
@@ -791,45 +1971,146 @@ impl DeviceDetector {
 // Helpers
 // ---------------------------------------------------------------------------
 
-fn load_yaml<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
-    let content = std::fs::read_to_string(path)?;
-    Ok(serde_yaml::from_str(&content)?)
+fn bot_from_match<'a>(m: MatchResult<'a, BotData>) -> Bot<'a> {
+    Bot {
+        name: substitute(&m.data.name, &m.captures),
+        category: m.data.category.as_deref(),
+        url: m.data.url.as_deref(),
+        producer: m.data.producer.as_ref().map(|p| BotProducer {
+            name: p.name.as_deref(),
+            url: p.url.as_deref(),
+        }),
+    }
 }
 
-fn build_client_parser(path: &Path, kind: ClientType) -> Result<CompiledParser<ClientData>> {
+/// Build the device (and the rule behind it) for a brand/model match from
+/// device file `file`.
+fn device_from_match<'a>(
+    file: &'static str,
+    default_type: DeviceType,
+    m: BrandMatchResult<'a, DeviceBrandData, DeviceModelData>,
+) -> (Device<'a>, Option<RuleId>) {
+    let brand_data = m.brand_data;
+    let rule = RuleId {
+        file,
+        index: m.brand_index,
+        model: m.model_match.as_ref().map(|mm| mm.index),
+    };
+
+    // Device type precedence: model `device:`, then brand `device:`,
+    // then the file-level default (e.g. smartphone for mobiles.yml).
+    let device = if let Some(model_match) = m.model_match {
+        // Model regex matched — use model data, falling back to brand data.
+        let device_type = model_match
+            .data
+            .device_type
+            .or(brand_data.device_type)
+            .unwrap_or(default_type);
+        let brand = model_match
+            .data
+            .brand
+            .as_deref()
+            .unwrap_or(&*brand_data.brand);
+        let model = match &model_match.data.model_template {
+            Some(tpl) => substitute(tpl, &model_match.captures),
+            None => Cow::Borrowed(""),
+        };
+        Device {
+            kind: Some(device_type),
+            brand: Cow::Borrowed(brand),
+            model,
+        }
+    } else {
+        // Only brand regex matched, no specific model.
+        let device_type = brand_data.device_type.unwrap_or(default_type);
+        let model = match &brand_data.model_template {
+            Some(tpl) => substitute(tpl, &m.brand_captures),
+            None => Cow::Borrowed(""),
+        };
+        Device {
+            kind: Some(device_type),
+            brand: Cow::Borrowed(&brand_data.brand),
+            model,
+        }
+    };
+
+    (device, Some(rule))
+}
+
+/// Version extractors for `engine`, mirroring Matomo's `EngineVersion`
+/// parser: Gecko-family engines prefer the `rv:` token, then every engine
+/// looks for its own token followed by a version.
+fn engine_version_regexes(engine: &str) -> Result<Vec<Regex>> {
+    let token = match engine {
+        "Blink" => "Chr[o0]me|Chromium|Cronet".to_string(),
+        "Arachne" => r"Arachne/5\.".to_string(),
+        "LibWeb" => r"LibWeb\+LibJs".to_string(),
+        _ => regex::escape(engine),
+    };
+    let mut regexes = Vec::new();
+    if engine == "Gecko" || engine == "Clecko" {
+        regexes.push(Regex::new(
+            r"(?i)[ ](?:rv[: ]([0-9.]+)).*(?:g|cl)ecko/[0-9]{8,10}",
+        )?);
+    }
+    regexes.push(Regex::new(&format!(
+        r"(?i)(?:{})\s*/?\s*(\d+\.\d[.\d]*|\d{{1,7}}(?=\D|$))",
+        token
+    ))?);
+    Ok(regexes)
+}
+
+/// The model token of an Android UA: the text between the last `;` and
+/// ` Build/`, cut at any `/` suffix (`HUAWEI VNS-L31/V100R001` →
+/// `HUAWEI VNS-L31`).  `None` when the slot holds no model, e.g. just the
+/// Android version.
+fn android_build_model(ua: &str) -> Option<&str> {
+    let end = ua.find(" Build/")?;
+    let start = ua[..end].rfind(';').map_or(0, |i| i + 1);
+    let model = ua[start..end].split('/').next().unwrap_or("").trim();
+    let no_model = model.is_empty() || model.contains(['(', ')']) || model.starts_with("Android");
+    (!no_model).then_some(model)
+}
+
+fn build_client_parser(
+    entries: Vec<db::BrowserEntry>,
+    kind: ClientType,
+    options: &ParserOptions,
+) -> Result<CompiledParser<ClientData>> {
     // All client YAML files share the same flat-list schema with regex/name/version/engine.
     // We use BrowserEntry as a superset that works for all of them.
-    let entries: Vec<db::BrowserEntry> = load_yaml(path)?;
-    CompiledParser::build(entries.into_iter().map(|e| {
-        let (engine_default, engine_versions) = match e.engine {
-            Some(eng) => (eng.default, eng.versions),
-            None => (None, None),
-        };
-        (
-            e.regex,
-            ClientData {
-                kind,
-                name: e.name,
-                version_template: e.version,
-                engine_default,
-                engine_versions,
-            },
-        )
-    }))
+    CompiledParser::build(
+        entries.into_iter().map(|e| {
+            let (engine_default, engine_versions) = match e.engine {
+                Some(eng) => (eng.default, eng.versions),
+                None => (None, None),
+            };
+            (
+                e.regex,
+                ClientData {
+                    kind,
+                    name: e.name,
+                    version_template: e.version,
+                    engine_default,
+                    engine_versions,
+                },
+            )
+        }),
+        options,
+    )
 }
 
 /// Returns `(parser, brand_regex_strings)`.  The second element contains the
 /// raw regex patterns for each brand; callers that need a `preMatchOverall`
 /// prefilter use these to build a combined mega-regex.
 fn build_device_brand_parser(
-    path: &Path,
-    default_type: DeviceType,
+    brands: db::DeviceBrandMap,
+    interner: &Interner,
+    options: &ParserOptions,
 ) -> Result<(
     DeviceBrandParser<DeviceBrandData, DeviceModelData>,
     Vec<String>,
 )> {
-    let brands: db::DeviceBrandMap = load_yaml(path)?;
-
     // Collect brands that have a regex, preserving YAML insertion order (IndexMap).
     let brand_items: Vec<(String, String, db::DeviceBrandEntry)> = brands
         .into_iter()
@@ -849,11 +2130,9 @@ fn build_device_brand_parser(
         brand_items
             .into_par_iter()
             .map(|(brand_name, brand_regex_str, entry)| {
-                let device_type = entry
-                    .device
-                    .as_deref()
-                    .and_then(DeviceType::from_str)
-                    .or(Some(default_type));
+                // Only the brand's own `device:` is stored; the file-level
+                // default is applied at match time, after model and brand.
+                let device_type = entry.device.as_deref().and_then(DeviceType::from_str);
 
                 // Compile model regexes in parallel within each brand.
                 let model_entries: Vec<CompiledEntry<DeviceModelData>> = entry
@@ -866,8 +2145,13 @@ fn build_device_brand_parser(
                             model.device.as_deref().and_then(DeviceType::from_str);
                         Ok(CompiledEntry {
                             regex: model_regex,
+                            pattern: model.regex,
                             data: DeviceModelData {
-                                brand: model.brand,
+                                brand: model
+                                    .brand
+                                    .as_deref()
+                                    .filter(|b| !b.is_empty())
+                                    .map(|b| interner.intern(b)),
                                 model_template: model.model,
                                 device_type: model_device_type,
                             },
@@ -875,12 +2159,10 @@ fn build_device_brand_parser(
                     })
                     .collect::<Result<Vec<_>>>()?;
 
-                let brand_full_pattern = full_pattern(&brand_regex_str);
-
                 Ok((
-                    brand_full_pattern,
+                    brand_regex_str,
                     DeviceBrandData {
-                        brand: brand_name,
+                        brand: interner.intern(&brand_name),
                         model_template: entry.model,
                         device_type,
                     },
@@ -890,7 +2172,70 @@ fn build_device_brand_parser(
             .collect::<Result<Vec<_>>>()?;
 
     Ok((
-        DeviceBrandParser::build(built_items)?,
+        DeviceBrandParser::build(built_items, options)?,
         brand_regex_strings,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn tv_client_name_reclassifies_as_tv() {
+        let mut dd = DeviceDetector::from_dir("tests/data/regexes").unwrap();
+        let ua = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+        let kind = |dd: &DeviceDetector| dd.parse(ua).device().and_then(|d| d.kind);
+        assert_eq!(kind(&dd), Some(DeviceType::Desktop));
+
+        dd.tv_clients.insert("Chrome".to_string());
+        assert_eq!(kind(&dd), Some(DeviceType::Tv));
+    }
+
+    #[test]
+    fn android_build_model_strips_suffixes() {
+        for (ua, expected) in [
+            ("(Android 11; SM-A525F Build/RP1A)", Some("SM-A525F")),
+            ("(en-us; VNS-L31/V100R001 Build/NRD90M)", Some("VNS-L31")),
+            ("(Linux; Android 4.4.2 Build/KOT49H)", None),
+            ("(Android 14; Pixel 8)", None),
+        ] {
+            assert_eq!(android_build_model(ua), expected, "{}", ua);
+        }
+    }
+
+    #[test]
+    fn device_brands_are_interned() {
+        let brands: db::DeviceBrandMap = serde_yaml::from_str(
+            r"
+Redmi:
+  regex: 'Redmi'
+  model: 'Redmi'
+Xiaomi:
+  regex: 'Xiaomi'
+  models:
+    - regex: 'Xiaomi 2201117TG'
+      brand: 'Redmi'
+      model: 'Note 11'
+    - regex: 'Xiaomi 22101316G'
+      brand: 'Redmi'
+      model: 'Note 12 Pro'
+",
+        )
+        .unwrap();
+        let interner = Interner::default();
+        let (parser, _) =
+            build_device_brand_parser(brands, &interner, &ParserOptions::default()).unwrap();
+
+        let note11 = parser.match_first("Android 12; Xiaomi 2201117TG").unwrap();
+        let note12 = parser.match_first("Android 13; Xiaomi 22101316G").unwrap();
+        let a = note11.model_match.unwrap().data.brand.as_ref().unwrap();
+        let b = note12.model_match.unwrap().data.brand.as_ref().unwrap();
+        assert_eq!(&**a, "Redmi");
+        assert!(Arc::ptr_eq(a, b));
+
+        let redmi = parser.brands().next().unwrap();
+        assert!(Arc::ptr_eq(a, &redmi.brand));
+    }
+}
@@ -10,6 +10,8 @@ pub enum Error {
     RegexFilteredParse(#[from] regex_filtered::ParseError),
     #[error(transparent)]
     RegexFilteredBuild(#[from] regex_filtered::BuildError),
+    #[error("unknown client type: {0}")]
+    UnknownClientType(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
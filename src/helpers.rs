@@ -1,30 +1,33 @@
 use crate::parser::Captures;
 use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
 
-/// Simple semver-ish comparison: is `a < b`?  Compares dot-separated numeric
-/// components left to right (missing components treated as 0).
-pub(crate) fn version_lt(a: &str, b: &str) -> bool {
-    let mut ai = a.split('.');
-    let mut bi = b.split('.');
+/// Compare two dot-separated versions component by component, numerically.
+/// `_` also separates components, as in iOS versions ("17_1" == "17.1").
+/// Missing and empty components count as 0, so "1" == "1.0" and "1..2" ==
+/// "1.0.2"; leading zeros are ignored ("08" == "8").
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let component = |c: Option<&str>| c.and_then(|c| c.parse::<u32>().ok()).unwrap_or(0);
+    let mut ai = a.split(['.', '_']);
+    let mut bi = b.split(['.', '_']);
     loop {
-        match (ai.next(), bi.next()) {
-            (None, None) => return false,
-            (None, Some(bv)) => return bv.parse::<u32>().unwrap_or(0) > 0,
-            (Some(_), None) => return false,
-            (Some(av), Some(bv)) => {
-                let an = av.parse::<u32>().unwrap_or(0);
-                let bn = bv.parse::<u32>().unwrap_or(0);
-                if an < bn {
-                    return true;
-                }
-                if an > bn {
-                    return false;
-                }
-            }
+        let (av, bv) = match (ai.next(), bi.next()) {
+            (None, None) => return Ordering::Equal,
+            (av, bv) => (component(av), component(bv)),
+        };
+        if av != bv {
+            return av.cmp(&bv);
         }
     }
 }
 
+/// Simple semver-ish comparison: is `a < b`?  See [`compare_versions`].
+pub(crate) fn version_lt(a: &str, b: &str) -> bool {
+    compare_versions(a, b) == Ordering::Less
+}
+
 /// Simple semver-ish comparison: is `a >= b`?
 pub(crate) fn version_ge(a: &str, b: &str) -> bool {
     !version_lt(a, b)
@@ -36,3 +39,99 @@ pub(crate) fn capture_or_empty<'a>(captures: &Captures<'a>, group: usize) -> Cow
         .map(Cow::Borrowed)
         .unwrap_or(Cow::Borrowed(""))
 }
+
+/// First capture group starting with a digit, the version of an entry
+/// without a `version` template.  Group 1 is not always the version (it can
+/// be an engine or edition name), so it is not taken blindly.
+pub(crate) fn first_numeric_capture<'a>(captures: &Captures<'a>) -> Cow<'a, str> {
+    (1..captures.len())
+        .filter_map(|i| captures.get_str(i))
+        .find(|s| s.starts_with(|c: char| c.is_ascii_digit()))
+        .map_or(Cow::Borrowed(""), Cow::Borrowed)
+}
+
+/// `Cow::Owned(value)`, counted by the `timing` feature.
+pub(crate) fn owned<'a>(value: String) -> Cow<'a, str> {
+    #[cfg(feature = "timing")]
+    crate::timing::record_owned();
+    Cow::Owned(value)
+}
+
+/// Shares one allocation per distinct string, e.g. brand names repeated
+/// across device files and model-level `brand:` overrides.  Safe to use from
+/// the parallel parser builds.
+#[derive(Default)]
+pub(crate) struct Interner(Mutex<HashSet<Arc<str>>>);
+
+impl Interner {
+    pub fn intern(&self, s: &str) -> Arc<str> {
+        let mut set = self.0.lock().unwrap();
+        if let Some(existing) = set.get(s) {
+            return Arc::clone(existing);
+        }
+        let interned: Arc<str> = Arc::from(s);
+        set.insert(Arc::clone(&interned));
+        interned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_zeros_are_equal() {
+        assert!(!version_lt("1.0", "1"));
+        assert!(!version_lt("1", "1.0"));
+        assert!(version_ge("1", "1.0.0"));
+    }
+
+    #[test]
+    fn shorter_version_compares_remaining_components() {
+        assert!(version_lt("1", "1.0.1"));
+        assert!(!version_lt("1.0.1", "1"));
+    }
+
+    #[test]
+    fn empty_components_are_zero() {
+        assert!(!version_lt("1..2", "1.0.2"));
+        assert!(!version_lt("1.0.2", "1..2"));
+        assert!(version_lt("1..2", "1.0.3"));
+    }
+
+    #[test]
+    fn leading_zeros_are_ignored() {
+        assert!(!version_lt("08", "8"));
+        assert!(!version_lt("8", "08"));
+        assert!(version_lt("08", "10"));
+    }
+
+    #[test]
+    fn compare_versions_orders_numerically() {
+        assert_eq!(compare_versions("10.0", "9.3"), Ordering::Greater);
+        assert_eq!(compare_versions("17", "17.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("15.7", "16"), Ordering::Less);
+        assert_eq!(compare_versions("17_1_2", "17.1.2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn first_numeric_capture_skips_named_groups() {
+        let re = regex::Regex::new(r"Lunascape/(Trident|WebKit)? ?(\d+[\.\d]+)").unwrap();
+        let caps = Captures::Standard(re.captures("Lunascape/WebKit 6.15.2").unwrap());
+        assert_eq!(first_numeric_capture(&caps), "6.15.2");
+        let caps = Captures::Standard(re.captures("Lunascape/6.15.2").unwrap());
+        assert_eq!(first_numeric_capture(&caps), "6.15.2");
+        let re = regex::Regex::new(r"(Trident)").unwrap();
+        let caps = Captures::Standard(re.captures("Trident").unwrap());
+        assert_eq!(first_numeric_capture(&caps), "");
+    }
+
+    #[test]
+    fn interner_shares_allocations() {
+        let interner = Interner::default();
+        let a = interner.intern("Xiaomi");
+        let b = interner.intern(&String::from("Xiaomi"));
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &interner.intern("Redmi")));
+    }
+}
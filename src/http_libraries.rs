@@ -0,0 +1,10 @@
+/// HTTP client libraries that Matomo's `libraries.yml` does not list.
+///
+/// Compiled into a client parser checked right after `libraries.yml`, so
+/// dataset entries win whenever both match.  The version comes from capture
+/// group 1 when present.
+pub(crate) const HTTP_LIBRARIES: &[(&str, &str)] = &[
+    (r"reqwest(?:/(\d+[\.\d]+))?", "reqwest"),
+    (r"ureq(?:/(\d+[\.\d]+))?", "ureq"),
+    (r"isahc(?:/(\d+[\.\d]+))?", "isahc"),
+];
@@ -1,14 +1,30 @@
+mod browser_extensions;
+mod builder;
+mod dataset;
+mod dataset_version;
 mod db;
 mod device_detector;
 mod device_prefilter;
 mod error;
 mod helpers;
+mod http_libraries;
 mod os_helpers;
 mod parser;
 mod parser_data;
+#[cfg(feature = "precompiled")]
+mod precompiled;
+mod stage;
 mod substitution;
+#[cfg(feature = "timing")]
+mod timing;
+mod tv_clients;
 mod types;
+mod validate;
 
+pub use builder::{DeviceDetectorBuilder, PostHookFn, VersionFormatterFn};
 pub use device_detector::DeviceDetector;
 pub use error::{Error, Result};
+pub use helpers::compare_versions;
+pub use parser::matches_with_boundary;
+pub use stage::{DetectionStage, PipelineStage};
 pub use types::*;
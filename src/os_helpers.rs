@@ -1,175 +1,427 @@
-/// Returns `true` if the given OS name belongs to a desktop OS family.
+/// OS families and their member OS names, derived from Matomo's
+/// `OperatingSystem::$osFamilies`.
 ///
-/// Derived from Matomo's `OperatingSystem::$desktopOsArray` and `$osFamilies`.
-/// Comparison is case-insensitive because OS names may come from regex captures.
+/// This is the single source of truth for the `is_*_os` predicates below.
+/// Names are matched case-insensitively because OS names may come from regex
+/// captures.
+const OS_FAMILIES: &[(&str, &[&str])] = &[
+    (
+        "Android",
+        &[
+            "Android",
+            "Android TV",
+            "Armadillo OS",
+            "Baidu Yi",
+            "Bliss OS",
+            "ClearOS Mobile",
+            "CyanogenMod",
+            "Fire OS",
+            "GridOS",
+            "HarmonyOS",
+            "HELIX OS",
+            "LeafOS",
+            "Lineage OS",
+            "Meta Horizon",
+            "MildWild",
+            "MocorDroid",
+            "OpenHarmony",
+            "PICO OS",
+            "Puffin OS",
+            "RazoDroiD",
+            "Remix OS",
+            "Resurrection Remix OS",
+            "Revenge OS",
+            "risingOS",
+            "Sirin OS",
+            "Smartisan OS",
+            "Wear OS",
+            "YunOS",
+        ],
+    ),
+    ("AmigaOS", &["AmigaOS", "AROS", "MorphOS"]),
+    ("BlackBerry", &["BlackBerry OS", "BlackBerry Tablet OS"]),
+    ("BeOS", &["BeOS", "Haiku OS"]),
+    (
+        "Chrome OS",
+        &["Chrome OS", "Chromium OS", "FydeOS", "Seewo OS", "SeewoOS"],
+    ),
+    ("Firefox OS", &["Firefox OS", "KaiOS"]),
+    (
+        "GNU/Linux",
+        &[
+            "AOSC OS",
+            "ASPLinux",
+            "Alpine Linux",
+            "Amazon Linux",
+            "Arch Linux",
+            "Azure Linux",
+            "BackTrack",
+            "BrightSignOS",
+            "Caixa Magica",
+            "CentOS",
+            "CentOS Stream",
+            "China OS",
+            "Clear Linux OS",
+            "Coolita OS",
+            "DVKBuntu",
+            "Debian",
+            "Deepin",
+            "EulerOS",
+            "FRITZ!OS",
+            "Fedora",
+            "Fenix",
+            "Foresight Linux",
+            "Freebox",
+            "Fuchsia",
+            "GNU/Linux",
+            "Gentoo",
+            "HasCodingOS",
+            "Joli OS",
+            "Kali",
+            "Kanotix",
+            "Knoppix",
+            "KreaTV",
+            "Kubuntu",
+            "LindowsOS",
+            "Linpus",
+            "Linspire",
+            "Liri OS",
+            "Loongnix",
+            "Lubuntu",
+            "Lumin OS",
+            "Mageia",
+            "Mandriva",
+            "Mint",
+            "Motorola EZX",
+            "Nova",
+            "OpenVZ",
+            "OpenWrt",
+            "Opera TV",
+            "Oracle Linux",
+            "Ordissimo",
+            "PCLinuxOS",
+            "Pardus",
+            "Plasma Mobile",
+            "postmarketOS",
+            "Proxmox VE",
+            "PureOS",
+            "Qtopia",
+            "Raspberry Pi OS",
+            "Raspbian",
+            "Red Hat",
+            "Red Star",
+            "RedOS",
+            "Rocky Linux",
+            "Roku OS",
+            "Rosa",
+            "RouterOS",
+            "SUSE",
+            "Sabayon",
+            "Sailfish OS",
+            "Scientific Linux",
+            "SerenityOS",
+            "Slackware",
+            "TencentOS",
+            "TiVo OS",
+            "TmaxOS",
+            "Turbolinux",
+            "UOS",
+            "Ubuntu",
+            "Ubuntu Touch",
+            "VIDAA",
+            "VectorLinux",
+            "ViziOS",
+            "Webian",
+            "WoPhone",
+            "Xubuntu",
+            "Zenwalk",
+            "ZorinOS",
+            "blackPanther OS",
+            "elementary OS",
+            "gNewSense",
+            "moonOS",
+            "openSUSE",
+        ],
+    ),
+    ("IBM", &["ArcaOS", "OS/2"]),
+    ("iOS", &["iOS", "iPadOS", "tvOS", "watchOS"]),
+    ("Mac", &["Mac"]),
+    ("OpenVMS", &["OpenVMS"]),
+    (
+        "Symbian",
+        &[
+            "Symbian",
+            "Symbian OS",
+            "Symbian OS Series 60",
+            "Symbian OS Series 40",
+            "Symbian^3",
+        ],
+    ),
+    (
+        "Unix",
+        &[
+            "AIX",
+            "BSD",
+            "DragonFly",
+            "ElectroBSD",
+            "FreeBSD",
+            "Genix",
+            "GhostBSD",
+            "HP-UX",
+            "IRIX",
+            "Inferno",
+            "MINIX",
+            "NEWS-OS",
+            "NeXTSTEP",
+            "NetBSD",
+            "OSF1",
+            "OpenBSD",
+            "Plan 9",
+            "Solaris",
+            "Star-Blade OS",
+            "Syllable",
+            "ULTRIX",
+        ],
+    ),
+    ("Windows", &["Windows"]),
+    (
+        "Windows Mobile",
+        &[
+            "Windows Phone",
+            "Windows Mobile",
+            "Windows CE",
+            "Windows RT",
+            "Windows IoT",
+            "KIN OS",
+        ],
+    ),
+];
+
+/// Families Matomo treats as desktop (`OperatingSystem::$desktopOsArray`).
+const DESKTOP_FAMILIES: &[&str] = &[
+    "AmigaOS",
+    "IBM",
+    "GNU/Linux",
+    "Mac",
+    "Unix",
+    "Windows",
+    "BeOS",
+    "Chrome OS",
+    "OpenVMS",
+];
+
+/// GNU/Linux-family systems built for phones and tablets.  Matomo counts
+/// them as desktop through their family; they are excluded here.
+const MOBILE_LINUX: &[&str] = &[
+    "Sailfish OS",
+    "Ubuntu Touch",
+    "postmarketOS",
+    "Plasma Mobile",
+];
+
+/// Operating systems that only run on feature phones.
+const FEATURE_PHONE_OSES: &[&str] = &["Java ME", "KaiOS"];
+
+/// Families whose members run on handheld devices.
+const MOBILE_FAMILIES: &[&str] = &[
+    "Android",
+    "iOS",
+    "Windows Mobile",
+    "Symbian",
+    "BlackBerry",
+    "Firefox OS",
+];
+
+/// Returns the Matomo OS family the given OS name belongs to, if any.
+pub(crate) fn os_family(os_name: &str) -> Option<&'static str> {
+    OS_FAMILIES
+        .iter()
+        .find(|(_, members)| members.iter().any(|m| m.eq_ignore_ascii_case(os_name)))
+        .map(|(family, _)| *family)
+}
+
+/// Returns `true` if the given OS name belongs to a desktop OS family and is
+/// not one of the mobile Linux systems.
 pub(crate) fn is_desktop_os(os_name: &str) -> bool {
-    // All comparisons are lowercase. The allocation is fine here since this
-    // only runs when no device brand was matched.
-    matches!(
-        os_name.to_ascii_lowercase().as_str(),
-        // AmigaOS family
-        "amigaos" | "aros" | "morphos"
-        // IBM family
-        | "arcaos" | "os/2"
-        // GNU/Linux family
-        | "aosc os"
-        | "asplinux"
-        | "alpine linux"
-        | "amazon linux"
-        | "arch linux"
-        | "azure linux"
-        | "backtrack"
-        | "brightsignos"
-        | "caixa magica"
-        | "centos"
-        | "centos stream"
-        | "china os"
-        | "clear linux os"
-        | "coolita os"
-        | "dvkbuntu"
-        | "debian"
-        | "deepin"
-        | "euleros"
-        | "fritz!os"
-        | "fedora"
-        | "fenix"
-        | "foresight linux"
-        | "freebox"
-        | "fuchsia"
-        | "gnu/linux"
-        | "gentoo"
-        | "hascodingos"
-        | "joli os"
-        | "kali"
-        | "kanotix"
-        | "knoppix"
-        | "kreatv"
-        | "kubuntu"
-        | "lindowsos"
-        | "linpus"
-        | "linspire"
-        | "liri os"
-        | "loongnix"
-        | "lubuntu"
-        | "lumin os"
-        | "mageia"
-        | "mandriva"
-        | "mint"
-        | "motorola ezx"
-        | "nova"
-        | "openvz"
-        | "openwrt"
-        | "opera tv"
-        | "oracle linux"
-        | "ordissimo"
-        | "pclinuxos"
-        | "pardus"
-        | "plasma mobile"
-        | "proxmox ve"
-        | "pureos"
-        | "qtopia"
-        | "raspberry pi os"
-        | "raspbian"
-        | "red hat"
-        | "red star"
-        | "redos"
-        | "rocky linux"
-        | "roku os"
-        | "rosa"
-        | "routeros"
-        | "suse"
-        | "sabayon"
-        | "sailfish os"
-        | "scientific linux"
-        | "serenityos"
-        | "slackware"
-        | "tencentos"
-        | "tivo os"
-        | "tmaxos"
-        | "turbolinux"
-        | "uos"
-        | "ubuntu"
-        | "vidaa"
-        | "vectorlinux"
-        | "vizios"
-        | "webian"
-        | "wophone"
-        | "xubuntu"
-        | "zenwalk"
-        | "zorinos"
-        | "blackpanther os"
-        | "elementary os"
-        | "gnewsense"
-        | "moonos"
-        | "opensuse"
-        // Mac family
-        | "mac"
-        // Unix family
-        | "aix"
-        | "bsd"
-        | "dragonfly"
-        | "electrobsd"
-        | "freebsd"
-        | "genix"
-        | "ghostbsd"
-        | "hp-ux"
-        | "irix"
-        | "inferno"
-        | "minix"
-        | "news-os"
-        | "nextstep"
-        | "netbsd"
-        | "osf1"
-        | "openbsd"
-        | "plan 9"
-        | "solaris"
-        | "star-blade os"
-        | "syllable"
-        | "ultrix"
-        // Windows family
-        | "windows"
-        // BeOS family
-        | "beos" | "haiku os"
-        // Chrome OS family
-        | "chrome os" | "chromium os" | "fydeos" | "seewoos"
-        // OpenVMS family
-        | "openvms"
-    )
+    !is_mobile_linux(os_name) && os_family(os_name).is_some_and(|f| DESKTOP_FAMILIES.contains(&f))
 }
 
 /// Returns `true` if the given OS name belongs to the Android OS family.
-///
-/// Derived from Matomo's `OperatingSystem::$osFamilies['Android']`.
 pub(crate) fn is_android_os(os_name: &str) -> bool {
-    matches!(
-        os_name,
-        "Android"
-            | "Android TV"
-            | "Armadillo OS"
-            | "Baidu Yi"
-            | "Bliss OS"
-            | "ClearOS Mobile"
-            | "CyanogenMod"
-            | "Fire OS"
-            | "GridOS"
-            | "HarmonyOS"
-            | "HELIX OS"
-            | "LeafOS"
-            | "Lineage OS"
-            | "Meta Horizon"
-            | "MildWild"
-            | "MocorDroid"
-            | "OpenHarmony"
-            | "PICO OS"
-            | "Puffin OS"
-            | "RazoDroiD"
-            | "Remix OS"
-            | "Resurrection Remix OS"
-            | "Revenge OS"
-            | "risingOS"
-            | "Sirin OS"
-            | "Smartisan OS"
-            | "Wear OS"
-            | "YunOS"
-    )
+    os_family(os_name) == Some("Android")
+}
+
+/// Returns `true` for Apple's own operating systems (iOS family and Mac),
+/// as checked by Matomo's Apple brand heuristics.
+pub(crate) fn is_apple_os(os_name: &str) -> bool {
+    matches!(os_family(os_name), Some("iOS") | Some("Mac"))
+}
+
+/// Returns `true` if the given OS name belongs to a mobile OS family or is
+/// one of the mobile Linux systems.
+pub(crate) fn is_mobile_os(os_name: &str) -> bool {
+    is_mobile_linux(os_name) || os_family(os_name).is_some_and(|f| MOBILE_FAMILIES.contains(&f))
+}
+
+/// Returns `true` for phone-first Linux systems (Sailfish OS, Ubuntu Touch,
+/// postmarketOS, Plasma Mobile).
+pub(crate) fn is_mobile_linux(os_name: &str) -> bool {
+    MOBILE_LINUX.iter().any(|m| m.eq_ignore_ascii_case(os_name))
+}
+
+/// Returns `true` for feature-phone operating systems (Java ME, KaiOS).
+pub(crate) fn is_feature_phone_os(os_name: &str) -> bool {
+    FEATURE_PHONE_OSES
+        .iter()
+        .any(|m| m.eq_ignore_ascii_case(os_name))
+}
+
+/// Map a `Sec-CH-UA-Platform` value to the OS name Matomo reports.
+///
+/// Returns `None` for an empty or `"Unknown"` platform, which browsers send
+/// when they won't reveal the OS; such a hint must not override the UA.
+pub(crate) fn os_name_from_platform_hint(platform: &str) -> Option<&str> {
+    let platform = platform.trim_matches('"').trim();
+    if platform.is_empty() || platform.eq_ignore_ascii_case("unknown") {
+        return None;
+    }
+    Some(match platform.to_ascii_lowercase().as_str() {
+        "macos" | "mac os" | "mac os x" => "Mac",
+        "linux" => "GNU/Linux",
+        "chrome os" | "chromeos" | "chromium os" => "Chrome OS",
+        "windows" => "Windows",
+        "android" => "Android",
+        "ios" => "iOS",
+        "fuchsia" => "Fuchsia",
+        _ => platform,
+    })
+}
+
+/// Normalise a `Sec-CH-UA-Platform-Version` for the given OS name.
+///
+/// Windows reports its UAPI release rather than the marketing version:
+/// 1–10 → "10", 13+ → "11", 0 → unknown (Matomo's `OperatingSystem.php`).
+pub(crate) fn os_version_from_platform_hint(os_name: &str, version: &str) -> String {
+    let version = version.trim_matches('"').trim();
+    if os_name != "Windows" {
+        return version.to_string();
+    }
+    let major: u32 = version
+        .split('.')
+        .next()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    match major {
+        0 => String::new(),
+        1..=10 => "10".to_string(),
+        _ => "11".to_string(),
+    }
+}
+
+/// Oldest Android release each Chrome major version supports, newest first:
+/// `(first Chrome major, Android version)`.
+const CHROME_MIN_ANDROID: &[(u32, &str)] = &[
+    (139, "10"),
+    (120, "8.0"),
+    (107, "7.0"),
+    (96, "6.0"),
+    (82, "5.0"),
+    (72, "4.4"),
+    (42, "4.1"),
+    (0, "4.0"),
+];
+
+/// Lower-bound Android version for a device running Chrome `chrome_major`.
+pub(crate) fn android_version_for_chrome(chrome_major: u32) -> &'static str {
+    CHROME_MIN_ANDROID
+        .iter()
+        .find(|(first, _)| chrome_major >= *first)
+        .map_or("4.0", |(_, version)| version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn android_version_from_chrome_major() {
+        assert_eq!(android_version_for_chrome(141), "10");
+        assert_eq!(android_version_for_chrome(120), "8.0");
+        assert_eq!(android_version_for_chrome(119), "7.0");
+        assert_eq!(android_version_for_chrome(81), "4.4");
+        assert_eq!(android_version_for_chrome(30), "4.0");
+    }
+
+    #[test]
+    fn android_family() {
+        assert!(is_android_os("Android"));
+        assert!(is_android_os("Fire OS"));
+        assert!(is_android_os("HarmonyOS"));
+        assert!(!is_android_os("iOS"));
+        assert!(!is_android_os("GNU/Linux"));
+    }
+
+    #[test]
+    fn desktop_family() {
+        assert!(is_desktop_os("Chrome OS"));
+        assert!(is_desktop_os("Windows"));
+        assert!(is_desktop_os("ubuntu"));
+        assert!(is_desktop_os("Mac"));
+        assert!(!is_desktop_os("Android"));
+        assert!(!is_desktop_os("Windows Phone"));
+        assert!(!is_desktop_os("iOS"));
+    }
+
+    #[test]
+    fn mobile_linux_is_not_desktop() {
+        assert_eq!(os_family("Sailfish OS"), Some("GNU/Linux"));
+        assert!(!is_desktop_os("Sailfish OS"));
+        assert!(!is_desktop_os("Ubuntu Touch"));
+        assert!(is_mobile_os("postmarketOS"));
+        assert!(is_desktop_os("GNU/Linux"));
+        assert!(!is_mobile_os("Ubuntu"));
+    }
+
+    #[test]
+    fn apple_family() {
+        assert!(is_apple_os("iOS"));
+        assert!(is_apple_os("iPadOS"));
+        assert!(is_apple_os("tvOS"));
+        assert!(is_apple_os("Mac"));
+        assert!(!is_apple_os("Windows"));
+        assert!(!is_apple_os(""));
+    }
+
+    #[test]
+    fn mobile_family() {
+        assert!(is_mobile_os("Android"));
+        assert!(is_mobile_os("Fire OS"));
+        assert!(is_mobile_os("iOS"));
+        assert!(is_mobile_os("Windows Phone"));
+        assert!(is_mobile_os("KaiOS"));
+        assert!(!is_mobile_os("Chrome OS"));
+        assert!(!is_mobile_os("Windows"));
+    }
+
+    #[test]
+    fn platform_hint_names() {
+        assert_eq!(os_name_from_platform_hint("\"macOS\""), Some("Mac"));
+        assert_eq!(os_name_from_platform_hint("Linux"), Some("GNU/Linux"));
+        assert_eq!(os_name_from_platform_hint("\"Unknown\""), None);
+        assert_eq!(os_name_from_platform_hint(""), None);
+    }
+
+    #[test]
+    fn windows_platform_versions() {
+        assert_eq!(os_version_from_platform_hint("Windows", "15.0.0"), "11");
+        assert_eq!(os_version_from_platform_hint("Windows", "10.0.0"), "10");
+        assert_eq!(os_version_from_platform_hint("Windows", "0.3.0"), "");
+        assert_eq!(os_version_from_platform_hint("Android", "14.0.0"), "14.0.0");
+    }
+
+    #[test]
+    fn unknown_os_has_no_family() {
+        assert_eq!(os_family("TempleOS"), None);
+        assert!(!is_desktop_os("TempleOS"));
+    }
 }
@@ -1,6 +1,7 @@
 use rayon::prelude::*;
 
 use crate::error::Result;
+use crate::types::DetectorStats;
 
 /// Matomo's word-boundary-like prefix applied to all regexes.
 /// Matches: start of string, or a non-alphanumeric boundary, or special prefixes.
@@ -18,6 +19,15 @@ pub(crate) fn compile_regex(pattern: &str) -> Result<fancy_regex::Regex> {
     Ok(fancy_regex::Regex::new(&full)?)
 }
 
+/// Test `pattern` against `ua` with the same boundary prefix and
+/// case-insensitivity the detector applies to every dataset regex.
+///
+/// Intended for checking supplementary rules: it compiles a fresh regex on
+/// every call, so keep it off hot paths.
+pub fn matches_with_boundary(ua: &str, pattern: &str) -> Result<bool> {
+    Ok(compile_regex(pattern)?.is_match(ua)?)
+}
+
 // ---------------------------------------------------------------------------
 // Captures — unified enum over regex::Captures and fancy_regex::Captures
 // ---------------------------------------------------------------------------
@@ -38,6 +48,86 @@ impl<'a> Captures<'a> {
             Captures::Fancy(c) => c.get(i).map(|m| m.as_str()),
         }
     }
+
+    /// Number of groups, including the implicit whole-match group 0.
+    pub fn len(&self) -> usize {
+        match self {
+            Captures::Standard(c) => c.len(),
+            Captures::Fancy(c) => c.len(),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Build options
+// ---------------------------------------------------------------------------
+
+/// Build-time knobs shared by `CompiledParser` and `DeviceBrandParser`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ParserOptions {
+    /// Discard entries that need fancy_regex instead of compiling them.
+    pub standard_only: bool,
+    /// Shortest literal (in bytes) regex-filtered's Aho-Corasick prefilter
+    /// keys on.  Patterns without a literal this long are checked against
+    /// every UA.
+    pub literal_min_len: usize,
+    /// Most fancy entries `match_first` tries per UA; `usize::MAX` for no cap.
+    pub max_fancy_checks: usize,
+}
+
+/// regex-filtered's own default minimum atom length.
+pub(crate) const DEFAULT_LITERAL_MIN_LEN: usize = 3;
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            standard_only: false,
+            literal_min_len: DEFAULT_LITERAL_MIN_LEN,
+            max_fancy_checks: usize::MAX,
+        }
+    }
+}
+
+/// Build the regex-filtered set over the patterns flagged in `is_standard`,
+/// with the entry index of each member.
+///
+/// regex-filtered can reject patterns the `regex` crate accepts (its
+/// prefilter analysis gives up on very large patterns).  Rather than failing
+/// the whole detector, the set is then left empty and the returned flag is
+/// set; [`merge_fallback`] hands every pattern to fancy_regex: slower, but
+/// the parser still matches.
+fn build_filtered(
+    full_patterns: &[String],
+    is_standard: &[bool],
+    literal_min_len: usize,
+) -> (regex_filtered::Regexes, Vec<usize>, bool) {
+    let build = || {
+        let mut builder = regex_filtered::Builder::new_atom_len(literal_min_len);
+        let mut filtered_to_entry: Vec<usize> = Vec::new();
+        for (idx, pattern) in full_patterns.iter().enumerate() {
+            if is_standard[idx] {
+                builder = builder.push(pattern).ok()?;
+                filtered_to_entry.push(idx);
+            }
+        }
+        Some((builder.build().ok()?, filtered_to_entry))
+    };
+    match build() {
+        Some((filtered, filtered_to_entry)) => (filtered, filtered_to_entry, false),
+        None => {
+            let empty = regex_filtered::Builder::new().build().expect("empty set");
+            (empty, Vec::new(), true)
+        }
+    }
+}
+
+/// Fancy entry indices once the regex-filtered set is empty: `fancy` plus
+/// every standard pattern, in entry order.  A no-op when there are no
+/// standard patterns.
+fn merge_fallback(mut fancy: Vec<usize>, is_standard: &[bool]) -> Vec<usize> {
+    fancy.extend((0..is_standard.len()).filter(|&i| is_standard[i]));
+    fancy.sort_unstable();
+    fancy
 }
 
 // ---------------------------------------------------------------------------
@@ -48,13 +138,19 @@ impl<'a> Captures<'a> {
 /// Used for model sub-regexes within a brand (small count, not on hot path).
 pub(crate) struct CompiledEntry<T> {
     pub regex: fancy_regex::Regex,
+    /// Source pattern as written in the YAML (without boundary prefix).
+    pub pattern: String,
     pub data: T,
 }
 
 /// Result of a successful match.
 pub(crate) struct MatchResult<'a, T> {
+    /// Position of the matching entry in the source list.
+    pub index: usize,
     pub data: &'a T,
     pub captures: Captures<'a>,
+    /// Source pattern of the matching entry.
+    pub pattern: &'a str,
 }
 
 // ---------------------------------------------------------------------------
@@ -74,14 +170,32 @@ pub(crate) struct CompiledParser<T> {
     fancy_entries: Vec<(usize, fancy_regex::Regex)>,
     /// Entry data indexed by entry index.
     data: Vec<T>,
+    /// Source patterns (without boundary prefix) indexed by entry index.
+    patterns: Vec<String>,
+    /// Optional `RegexSet` over the standard patterns: when it reports no
+    /// match, the regex-filtered pass is skipped entirely.
+    gate: Option<regex::RegexSet>,
+    /// Fancy-only entries discarded because of `ParserOptions::standard_only`.
+    dropped: usize,
+    /// regex-filtered rejected the standard patterns, so they are matched
+    /// as fancy entries (see `build_filtered`).
+    filtered_fallback: bool,
+    /// `ParserOptions::literal_min_len` the prefilter was built with.
+    literal_min_len: usize,
+    /// `ParserOptions::max_fancy_checks`.
+    max_fancy_checks: usize,
 }
 
 impl<T> CompiledParser<T> {
     /// Build a CompiledParser from an iterator of (regex_pattern, data) pairs.
     ///
     /// Patterns that compile with the `regex` crate go through regex-filtered
-    /// for fast Thompson-NFA matching; the rest fall back to fancy_regex.
-    pub fn build(items: impl IntoIterator<Item = (String, T)>) -> Result<Self>
+    /// for fast Thompson-NFA matching; the rest fall back to fancy_regex, or
+    /// are dropped when `options.standard_only` is set.
+    pub fn build(
+        items: impl IntoIterator<Item = (String, T)>,
+        options: &ParserOptions,
+    ) -> Result<Self>
     where
         T: Send,
     {
@@ -90,10 +204,12 @@ impl<T> CompiledParser<T> {
 
         // Phase 1: compute full patterns, separate data.
         let mut full_patterns: Vec<String> = Vec::with_capacity(n);
+        let mut patterns: Vec<String> = Vec::with_capacity(n);
         let mut data: Vec<T> = Vec::with_capacity(n);
 
         for (pattern, d) in items {
             full_patterns.push(full_pattern(&pattern));
+            patterns.push(pattern);
             data.push(d);
         }
 
@@ -104,20 +220,19 @@ impl<T> CompiledParser<T> {
             .collect();
 
         // Phase 3: build regex-filtered set from standard patterns.
-        let mut builder = regex_filtered::Builder::new();
-        let mut filtered_to_entry: Vec<usize> = Vec::new();
-
-        for (idx, pattern) in full_patterns.iter().enumerate() {
-            if is_standard[idx] {
-                builder = builder.push(pattern).expect("pre-validated pattern");
-                filtered_to_entry.push(idx);
-            }
-        }
-
-        let filtered = builder.build()?;
+        let (filtered, filtered_to_entry, filtered_fallback) =
+            build_filtered(&full_patterns, &is_standard, options.literal_min_len);
 
         // Phase 4: compile fancy-only patterns in parallel.
-        let fancy_indices: Vec<usize> = (0..n).filter(|&i| !is_standard[i]).collect();
+        let mut fancy_indices: Vec<usize> = (0..n).filter(|&i| !is_standard[i]).collect();
+        let dropped = if options.standard_only {
+            std::mem::take(&mut fancy_indices).len()
+        } else {
+            0
+        };
+        if filtered_fallback {
+            fancy_indices = merge_fallback(fancy_indices, &is_standard);
+        }
         let fancy_regexes: Vec<fancy_regex::Regex> = fancy_indices
             .par_iter()
             .map(|&idx| {
@@ -139,33 +254,75 @@ impl<T> CompiledParser<T> {
             filtered_to_entry,
             fancy_entries,
             data,
+            patterns,
+            gate: None,
+            dropped,
+            filtered_fallback,
+            literal_min_len: options.literal_min_len,
+            max_fancy_checks: options.max_fancy_checks,
         })
     }
 
+    pub fn stats(&self) -> DetectorStats {
+        DetectorStats {
+            standard_entries: self.filtered.regexes().len(),
+            fancy_entries: self.fancy_entries.len(),
+            dropped_entries: self.dropped,
+            filtered_fallbacks: usize::from(self.filtered_fallback),
+        }
+    }
+
+    /// Entry data in file order.
+    pub fn entries(&self) -> impl Iterator<Item = &T> {
+        self.data.iter()
+    }
+
+    /// Build the `RegexSet` gate over the standard patterns.
+    ///
+    /// Whether this pays off depends on the hit rate: it saves the
+    /// regex-filtered pass for UAs that match nothing, at the cost of an extra
+    /// DFA scan for those that do.  If the set exceeds the regex crate's size
+    /// limits the gate is silently left disabled.
+    pub fn enable_regex_set_gate(&mut self) {
+        let patterns = self.filtered.regexes().iter().map(|re| re.as_str());
+        self.gate = regex::RegexSet::new(patterns).ok();
+    }
+
     /// Find the first matching entry (preserving original order).
+    ///
+    /// At most `max_fancy_checks` fancy entries are tried; once they are
+    /// used up the standard match (if any) is returned.
     pub fn match_first<'a>(&'a self, ua: &'a str) -> Option<MatchResult<'a, T>> {
         // Get the first (lowest entry-index) match from regex-filtered.
         // filtered_to_entry is monotonically increasing, and matching()
         // returns results in ascending filtered-index order, so the first
         // hit corresponds to the lowest entry index among standard patterns.
         let mut best_filtered: Option<(usize, &regex::Regex)> = None;
-        for (filtered_idx, re) in self.filtered.matching(ua) {
-            let entry_idx = self.filtered_to_entry[filtered_idx];
-            best_filtered = Some((entry_idx, re));
-            break;
+        let gated_out = self.gate.as_ref().is_some_and(|g| !g.is_match(ua));
+        if !gated_out {
+            for (filtered_idx, re) in self.filtered.matching(ua) {
+                let entry_idx = self.filtered_to_entry[filtered_idx];
+                best_filtered = Some((entry_idx, re));
+                break;
+            }
         }
 
         let cutoff = best_filtered.map(|(idx, _)| idx).unwrap_or(usize::MAX);
 
+        let mut budget = self.max_fancy_checks;
+
         // Try fancy entries whose index is lower than the best filtered match.
         for &(entry_idx, ref re) in &self.fancy_entries {
-            if entry_idx >= cutoff {
+            if entry_idx >= cutoff || budget == 0 {
                 break;
             }
-            if let Ok(Some(caps)) = re.captures(ua) {
+            budget -= 1;
+            if let Some(caps) = fancy_captures(re, ua) {
                 return Some(MatchResult {
+                    index: entry_idx,
                     data: &self.data[entry_idx],
                     captures: Captures::Fancy(caps),
+                    pattern: &self.patterns[entry_idx],
                 });
             }
         }
@@ -174,8 +331,10 @@ impl<T> CompiledParser<T> {
         if let Some((entry_idx, re)) = best_filtered {
             if let Some(caps) = re.captures(ua) {
                 return Some(MatchResult {
+                    index: entry_idx,
                     data: &self.data[entry_idx],
                     captures: Captures::Standard(caps),
+                    pattern: &self.patterns[entry_idx],
                 });
             }
         }
@@ -188,10 +347,16 @@ impl<T> CompiledParser<T> {
                 if entry_idx <= cutoff {
                     continue;
                 }
-                if let Ok(Some(caps)) = re.captures(ua) {
+                if budget == 0 {
+                    break;
+                }
+                budget -= 1;
+                if let Some(caps) = fancy_captures(re, ua) {
                     return Some(MatchResult {
+                        index: entry_idx,
                         data: &self.data[entry_idx],
                         captures: Captures::Fancy(caps),
+                        pattern: &self.patterns[entry_idx],
                     });
                 }
             }
@@ -199,6 +364,49 @@ impl<T> CompiledParser<T> {
 
         None
     }
+
+    /// Every matching entry, in entry order.
+    pub fn match_all<'a>(&'a self, ua: &'a str) -> Vec<MatchResult<'a, T>> {
+        let mut matches = Vec::new();
+        let gated_out = self.gate.as_ref().is_some_and(|g| !g.is_match(ua));
+        if !gated_out {
+            for (filtered_idx, re) in self.filtered.matching(ua) {
+                let entry_idx = self.filtered_to_entry[filtered_idx];
+                if let Some(caps) = re.captures(ua) {
+                    matches.push(MatchResult {
+                        index: entry_idx,
+                        data: &self.data[entry_idx],
+                        captures: Captures::Standard(caps),
+                        pattern: &self.patterns[entry_idx],
+                    });
+                }
+            }
+        }
+        for &(entry_idx, ref re) in &self.fancy_entries {
+            if let Ok(Some(caps)) = re.captures(ua) {
+                matches.push(MatchResult {
+                    index: entry_idx,
+                    data: &self.data[entry_idx],
+                    captures: Captures::Fancy(caps),
+                    pattern: &self.patterns[entry_idx],
+                });
+            }
+        }
+        matches.sort_by_key(|m| m.index);
+        matches
+    }
+
+    /// Standard entries whose literal appears in `ua` but whose regex does
+    /// not match, as `(entry index, source pattern)`.
+    pub fn near_misses<'a>(&'a self, ua: &str) -> Vec<(usize, &'a str)> {
+        near_misses(
+            &self.filtered,
+            &self.filtered_to_entry,
+            |idx| &self.patterns[idx],
+            ua,
+            self.literal_min_len,
+        )
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -208,14 +416,20 @@ impl<T> CompiledParser<T> {
 /// Brand entry: data + model sub-regexes (gate regex handled by regex-filtered).
 pub(crate) struct BrandEntry<B, M> {
     pub data: B,
+    /// Source gate pattern (without boundary prefix).
+    pub pattern: String,
     pub models: Vec<CompiledEntry<M>>,
 }
 
 /// Result of a device brand match.
 pub(crate) struct BrandMatchResult<'a, B, M> {
+    /// Position of the matching brand in the source file.
+    pub brand_index: usize,
     pub brand_data: &'a B,
     /// Captures from the brand regex (used if no model matches).
     pub brand_captures: Captures<'a>,
+    /// Source pattern of the matching brand gate.
+    pub brand_pattern: &'a str,
     /// If a model regex matched, its data and captures.
     pub model_match: Option<MatchResult<'a, M>>,
 }
@@ -233,14 +447,29 @@ pub(crate) struct DeviceBrandParser<B, M> {
     fancy_brands: Vec<(usize, fancy_regex::Regex)>,
     /// Brand data + models, indexed by brand index.
     brands: Vec<BrandEntry<B, M>>,
+    /// Fancy-only brand gates discarded because of `ParserOptions::standard_only`.
+    dropped: usize,
+    /// regex-filtered rejected the standard patterns, so they are matched
+    /// as fancy entries (see `build_filtered`).
+    filtered_fallback: bool,
+    /// `ParserOptions::literal_min_len` the prefilter was built with.
+    literal_min_len: usize,
+    /// `ParserOptions::max_fancy_checks`.
+    max_fancy_checks: usize,
 }
 
 impl<B, M> DeviceBrandParser<B, M> {
     /// Build a `DeviceBrandParser`.
     ///
-    /// Each item is `(full_matomo_pattern, brand_data, compiled_model_entries)`.
-    /// The full pattern includes the Matomo boundary prefix and `(?i)` flag.
-    pub fn build(items: Vec<(String, B, Vec<CompiledEntry<M>>)>) -> Result<Self>
+    /// Each item is `(brand_pattern, brand_data, compiled_model_entries)`.
+    /// The Matomo boundary prefix and `(?i)` flag are added here.
+    ///
+    /// With `options.standard_only`, brands whose gate needs fancy_regex are
+    /// never matched; their model regexes are unaffected.
+    pub fn build(
+        items: Vec<(String, B, Vec<CompiledEntry<M>>)>,
+        options: &ParserOptions,
+    ) -> Result<Self>
     where
         B: Send,
         M: Send,
@@ -250,8 +479,12 @@ impl<B, M> DeviceBrandParser<B, M> {
         let mut brands: Vec<BrandEntry<B, M>> = Vec::with_capacity(n);
 
         for (pattern, data, models) in items {
-            full_patterns.push(pattern);
-            brands.push(BrandEntry { data, models });
+            full_patterns.push(full_pattern(&pattern));
+            brands.push(BrandEntry {
+                data,
+                pattern,
+                models,
+            });
         }
 
         // Classify patterns.
@@ -261,20 +494,19 @@ impl<B, M> DeviceBrandParser<B, M> {
             .collect();
 
         // Build regex-filtered set from standard patterns.
-        let mut builder = regex_filtered::Builder::new();
-        let mut filtered_to_brand: Vec<usize> = Vec::new();
-
-        for (idx, pattern) in full_patterns.iter().enumerate() {
-            if is_standard[idx] {
-                builder = builder.push(pattern).expect("pre-validated pattern");
-                filtered_to_brand.push(idx);
-            }
-        }
-
-        let filtered = builder.build()?;
+        let (filtered, filtered_to_brand, filtered_fallback) =
+            build_filtered(&full_patterns, &is_standard, options.literal_min_len);
 
         // Compile fancy-only patterns in parallel.
-        let fancy_indices: Vec<usize> = (0..n).filter(|&i| !is_standard[i]).collect();
+        let mut fancy_indices: Vec<usize> = (0..n).filter(|&i| !is_standard[i]).collect();
+        let dropped = if options.standard_only {
+            std::mem::take(&mut fancy_indices).len()
+        } else {
+            0
+        };
+        if filtered_fallback {
+            fancy_indices = merge_fallback(fancy_indices, &is_standard);
+        }
         let fancy_regexes: Vec<fancy_regex::Regex> = fancy_indices
             .par_iter()
             .map(|&idx| {
@@ -290,34 +522,75 @@ impl<B, M> DeviceBrandParser<B, M> {
             filtered_to_brand,
             fancy_brands,
             brands,
+            dropped,
+            filtered_fallback,
+            literal_min_len: options.literal_min_len,
+            max_fancy_checks: options.max_fancy_checks,
         })
     }
 
+    pub fn stats(&self) -> DetectorStats {
+        DetectorStats {
+            standard_entries: self.filtered.regexes().len(),
+            fancy_entries: self.fancy_brands.len(),
+            dropped_entries: self.dropped,
+            filtered_fallbacks: usize::from(self.filtered_fallback),
+        }
+    }
+
+    /// Brand data in file order.
+    pub fn brands(&self) -> impl Iterator<Item = &B> {
+        self.brands.iter().map(|b| &b.data)
+    }
+
+    /// Standard brand gates whose literal appears in `ua` but whose regex
+    /// does not match, as `(brand index, source pattern)`.
+    pub fn near_misses<'a>(&'a self, ua: &str) -> Vec<(usize, &'a str)> {
+        near_misses(
+            &self.filtered,
+            &self.filtered_to_brand,
+            |idx| &self.brands[idx].pattern,
+            ua,
+            self.literal_min_len,
+        )
+    }
+
     /// Find the first matching brand, then try model regexes within it.
+    ///
+    /// At most `max_fancy_checks` fancy brand gates are tried; model regexes
+    /// do not count against the cap.
     pub fn match_first<'a>(&'a self, ua: &'a str) -> Option<BrandMatchResult<'a, B, M>> {
-        // Get the first (lowest brand-index) match from regex-filtered.
-        let mut best_filtered: Option<(usize, &regex::Regex)> = None;
-        for (filtered_idx, re) in self.filtered.matching(ua) {
-            let brand_idx = self.filtered_to_brand[filtered_idx];
-            best_filtered = Some((brand_idx, re));
-            break;
-        }
+        // Get the first (lowest brand-index) match from regex-filtered:
+        // `matching` yields in ascending filtered index, and filtered indices
+        // follow brand order.
+        let best_filtered: Option<(usize, &regex::Regex)> = self
+            .filtered
+            .matching(ua)
+            .next()
+            .map(|(filtered_idx, re)| (self.filtered_to_brand[filtered_idx], re));
 
         let cutoff = best_filtered.map(|(idx, _)| idx).unwrap_or(usize::MAX);
 
-        // Try fancy brands with index < cutoff.
+        let mut budget = self.max_fancy_checks;
+
+        // Try fancy brands with index < cutoff.  They are sorted, so the
+        // first hit is the lowest-index fancy match and beats the standard
+        // one; a standard brand at a lower index stops the loop early.
         for &(brand_idx, ref re) in &self.fancy_brands {
-            if brand_idx >= cutoff {
+            if brand_idx >= cutoff || budget == 0 {
                 break;
             }
+            budget -= 1;
             // Check if regex matches first before extracting captures
             if re.is_match(ua).unwrap_or(false) {
                 if let Ok(Some(caps)) = re.captures(ua) {
                     let brand = &self.brands[brand_idx];
                     let model_match = match_model(ua, &brand.models);
                     return Some(BrandMatchResult {
+                        brand_index: brand_idx,
                         brand_data: &brand.data,
                         brand_captures: Captures::Fancy(caps),
+                        brand_pattern: &brand.pattern,
                         model_match,
                     });
                 }
@@ -331,8 +604,10 @@ impl<B, M> DeviceBrandParser<B, M> {
                 let brand = &self.brands[brand_idx];
                 let model_match = match_model(ua, &brand.models);
                 return Some(BrandMatchResult {
+                    brand_index: brand_idx,
                     brand_data: &brand.data,
                     brand_captures: Captures::Standard(caps),
+                    brand_pattern: &brand.pattern,
                     model_match,
                 });
             }
@@ -344,14 +619,20 @@ impl<B, M> DeviceBrandParser<B, M> {
                 if brand_idx <= cutoff {
                     continue;
                 }
+                if budget == 0 {
+                    break;
+                }
+                budget -= 1;
                 // Check if regex matches first before extracting captures
                 if re.is_match(ua).unwrap_or(false) {
                     if let Ok(Some(caps)) = re.captures(ua) {
                         let brand = &self.brands[brand_idx];
                         let model_match = match_model(ua, &brand.models);
                         return Some(BrandMatchResult {
+                            brand_index: brand_idx,
                             brand_data: &brand.data,
                             brand_captures: Captures::Fancy(caps),
+                            brand_pattern: &brand.pattern,
                             model_match,
                         });
                     }
@@ -363,17 +644,31 @@ impl<B, M> DeviceBrandParser<B, M> {
     }
 }
 
+#[cfg(test)]
+thread_local! {
+    static FANCY_CHECKS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Run a fancy entry of a flat-list parser against `ua`; tests count calls.
+fn fancy_captures<'a>(re: &fancy_regex::Regex, ua: &'a str) -> Option<fancy_regex::Captures<'a>> {
+    #[cfg(test)]
+    FANCY_CHECKS.with(|n| n.set(n.get() + 1));
+    re.captures(ua).ok().flatten()
+}
+
 /// Try model regexes within a matched brand (stays as fancy_regex).
 /// Optimized to check for match first before extracting captures.
 fn match_model<'a, M>(ua: &'a str, models: &'a [CompiledEntry<M>]) -> Option<MatchResult<'a, M>> {
-    models.iter().find_map(|model| {
+    models.iter().enumerate().find_map(|(index, model)| {
         // First check if the regex matches (which is faster than capturing)
         if model.regex.is_match(ua).unwrap_or(false) {
             // Only extract captures if we know there's a match
             match model.regex.captures(ua) {
                 Ok(Some(caps)) => Some(MatchResult {
+                    index,
                     data: &model.data,
                     captures: Captures::Fancy(caps),
+                    pattern: &model.pattern,
                 }),
                 _ => None,
             }
@@ -382,3 +677,253 @@ fn match_model<'a, M>(ua: &'a str, models: &'a [CompiledEntry<M>]) -> Option<Mat
         }
     })
 }
+
+// ---------------------------------------------------------------------------
+// Near misses — prefilter hit, regex miss (dataset debugging)
+// ---------------------------------------------------------------------------
+
+/// Standard entries of a regex-filtered set whose required literals are in
+/// `ua` but whose full (boundary-prefixed) regex does not match.
+///
+/// regex-filtered keeps its atoms private, so they are approximated from
+/// the source pattern; entries without one are never reported.
+fn near_misses<'a>(
+    filtered: &regex_filtered::Regexes,
+    to_entry: &[usize],
+    pattern_of: impl Fn(usize) -> &'a str,
+    ua: &str,
+    literal_min_len: usize,
+) -> Vec<(usize, &'a str)> {
+    filtered
+        .regexes()
+        .iter()
+        .zip(to_entry)
+        .filter_map(|(re, &idx)| {
+            let pattern = pattern_of(idx);
+            let sets = required_literals(pattern, literal_min_len.max(1))?;
+            let present = sets.iter().all(|set| {
+                set.iter()
+                    .any(|lit| contains_ignore_ascii_case(ua.as_bytes(), lit))
+            });
+            (present && !re.is_match(ua)).then_some((idx, pattern))
+        })
+        .collect()
+}
+
+/// Literal sets `pattern` requires: its prefix literals and its suffix
+/// literals, one of each set appearing in any text it matches.  A set is
+/// kept only when it is finite and every literal is at least `min_len`
+/// bytes; `None` when neither is kept.
+fn required_literals(pattern: &str, min_len: usize) -> Option<Vec<Vec<Vec<u8>>>> {
+    use regex_syntax::hir::literal::{ExtractKind, Extractor};
+
+    let hir = regex_syntax::parse(pattern).ok()?;
+    let sets: Vec<Vec<Vec<u8>>> = [ExtractKind::Prefix, ExtractKind::Suffix]
+        .into_iter()
+        .filter_map(|kind| {
+            let seq = Extractor::new().kind(kind).extract(&hir);
+            let literals = seq.literals()?;
+            let usable = !literals.is_empty() && literals.iter().all(|l| l.len() >= min_len);
+            usable.then(|| literals.iter().map(|l| l.as_bytes().to_vec()).collect())
+        })
+        .collect();
+    (!sets.is_empty()).then_some(sets)
+}
+
+fn contains_ignore_ascii_case(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window.eq_ignore_ascii_case(needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(patterns: &[&str]) -> CompiledParser<usize> {
+        CompiledParser::build(
+            patterns.iter().enumerate().map(|(i, p)| (p.to_string(), i)),
+            &ParserOptions::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn earlier_fancy_entry_beats_later_standard_entry() {
+        // Mirrors browsers.yml: "Mobile Safari" (lookbehind → fancy_regex)
+        // precedes the generic "Safari" entry (regex-filtered).
+        let parser = build(&[
+            r"(?:iPod|(?<!Apple TV; U; CPU )iPhone|iPad).+Version/(\d+[\.\d]+)",
+            r"Version/(\d+[\.\d]+).*Safari/",
+        ]);
+
+        let iphone = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1";
+        assert_eq!(parser.match_first(iphone).map(|m| *m.data), Some(0));
+
+        let mac = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Safari/605.1.15";
+        assert_eq!(parser.match_first(mac).map(|m| *m.data), Some(1));
+    }
+
+    #[test]
+    fn match_all_returns_every_hit_in_entry_order() {
+        let parser = build(&[
+            r"Googlebot",
+            r"(?<!Headless)Chrome",
+            r"bot",
+            r"Chrome/(\d+)",
+        ]);
+        let ua = "Mozilla/5.0 (compatible; Googlebot/2.1) Chrome/120";
+        let hits: Vec<usize> = parser.match_all(ua).iter().map(|m| *m.data).collect();
+        assert_eq!(hits, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn literal_min_len_does_not_change_matches() {
+        // "TV" is shorter than the default 3-byte atom, so that entry is an
+        // always-candidate unless the minimum is lowered.
+        let patterns = [r"LG-([A-Z]?\d+)", r"TV", r"Googlebot", r"Chrome/(\d+)"];
+        let uas = [
+            "Mozilla/5.0 (Linux; Android 9; LG-H870) Chrome/120",
+            "Mozilla/5.0 (SMART TV; Linux) Chrome/96",
+            "Googlebot/2.1",
+            "curl/8.0",
+        ];
+        let first = |min_len| {
+            let options = ParserOptions {
+                literal_min_len: min_len,
+                ..ParserOptions::default()
+            };
+            let parser = CompiledParser::build(
+                patterns.iter().enumerate().map(|(i, p)| (p.to_string(), i)),
+                &options,
+            )
+            .unwrap();
+            uas.map(|ua| parser.match_first(ua).map(|m| *m.data))
+        };
+        let expected = [Some(0), Some(1), Some(2), None];
+        assert_eq!(first(1), expected);
+        assert_eq!(first(2), expected);
+        assert_eq!(first(DEFAULT_LITERAL_MIN_LEN), expected);
+        assert_eq!(first(usize::MAX), expected);
+    }
+
+    #[test]
+    fn near_misses_report_literal_hits_that_fail_to_match() {
+        let parser = build(&[r"SMART-TV; ([^;)]+)", r"Chrome/(\d+)", r"Googlebot"]);
+
+        // "SMART-TV" is in the UA, but the boundary prefix rejects the
+        // preceding '-'.
+        let ua = "Mozilla/5.0 (Linux; X-SMART-TV; Hisense) Chrome/96";
+        assert_eq!(parser.near_misses(ua), vec![(0, r"SMART-TV; ([^;)]+)")]);
+        assert!(parser.near_misses("Googlebot/2.1").is_empty());
+    }
+
+    #[test]
+    fn max_fancy_checks_caps_captures_calls() {
+        // 200 lookbehind entries that never match, then a standard one.
+        let mut patterns: Vec<String> = (0..200).map(|i| format!(r"(?<!x)Fancy{}Z", i)).collect();
+        patterns.push(r"Chrome/(\d+)".to_string());
+        let build_capped = |max_fancy_checks| {
+            let options = ParserOptions {
+                max_fancy_checks,
+                ..ParserOptions::default()
+            };
+            CompiledParser::build(
+                patterns.iter().enumerate().map(|(i, p)| (p.clone(), i)),
+                &options,
+            )
+            .unwrap()
+        };
+        let checks = |parser: &CompiledParser<usize>, ua| {
+            FANCY_CHECKS.with(|n| n.set(0));
+            let hit = parser.match_first(ua).map(|m| *m.data);
+            (hit, FANCY_CHECKS.with(|n| n.get()))
+        };
+
+        let ua = "Mozilla/5.0 Chrome/120";
+        assert_eq!(checks(&build_capped(usize::MAX), ua), (Some(200), 200));
+        // The standard match is still returned once the cap is hit.
+        assert_eq!(checks(&build_capped(16), ua), (Some(200), 16));
+        assert_eq!(checks(&build_capped(16), "curl/8.4.0"), (None, 16));
+        // A fancy entry past the cap is missed: the accuracy tradeoff.
+        assert_eq!(checks(&build_capped(16), "Fancy42Z"), (None, 16));
+        assert_eq!(checks(&build_capped(64), "Fancy42Z"), (Some(42), 43));
+    }
+
+    #[test]
+    fn regex_set_gate_preserves_first_match() {
+        let patterns = [
+            r"Googlebot(?:/(\d+[\.\d]+))?",
+            r"bingbot(?!/0)",
+            r"Chrome/(\d+)",
+            r"(?<!Headless)Firefox",
+            r"Yandex(?:Bot|Images)",
+        ];
+        let plain = build(&patterns);
+        let mut gated = build(&patterns);
+        gated.enable_regex_set_gate();
+        assert!(gated.gate.is_some());
+
+        for ua in [
+            "Mozilla/5.0 (compatible; Googlebot/2.1)",
+            "Mozilla/5.0 (compatible; bingbot/2.0)",
+            "Mozilla/5.0 Chrome/120.0 Safari/537.36",
+            "Mozilla/5.0 Gecko/20100101 Firefox/121.0",
+            "Mozilla/5.0 (compatible; YandexBot/3.0)",
+            "curl/8.4.0",
+            "",
+        ] {
+            let expected = plain.match_first(ua).map(|m| *m.data);
+            let actual = gated.match_first(ua).map(|m| *m.data);
+            assert_eq!(actual, expected, "gate changed result for {:?}", ua);
+        }
+    }
+
+    #[test]
+    fn rejected_filtered_set_falls_back_to_fancy_regex() {
+        assert_eq!(build(&[r"Chrome/(\d+)"]).stats().filtered_fallbacks, 0);
+
+        // Accepted by the regex crate, but far past the node budget of
+        // regex-filtered's prefilter analysis.
+        let huge = format!("Huge-{}", "x".repeat(120_000));
+        let parser = build(&[r"(?<!Android )Chrome/(\d+)", &huge, r"Firefox/(\d+)"]);
+        let stats = parser.stats();
+        assert_eq!((stats.standard_entries, stats.fancy_entries), (0, 3));
+        assert_eq!(stats.filtered_fallbacks, 1);
+
+        let index = |ua: &str| parser.match_first(ua).map(|m| m.index);
+        assert_eq!(index("Mozilla/5.0 Chrome/120"), Some(0));
+        assert_eq!(index("Mozilla/5.0 Firefox/121"), Some(2));
+        assert_eq!(index(&format!("App {}", &huge)), Some(1));
+        assert_eq!(index("Android Chrome/120"), None);
+    }
+
+    #[test]
+    fn device_brand_parser_prefers_lowest_index_gate() {
+        let build_brands = |patterns: &[&str]| {
+            let items = patterns
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (p.to_string(), i, Vec::<CompiledEntry<()>>::new()))
+                .collect();
+            DeviceBrandParser::build(items, &ParserOptions::default()).unwrap()
+        };
+        let brand = |parser: &DeviceBrandParser<usize, ()>, ua| {
+            parser.match_first(ua).map(|m| *m.brand_data)
+        };
+        let ua = "Mozilla/5.0 (Linux; Android 13; SM-A525F) Mobile";
+
+        // Standard gate at 0, fancy (lookbehind) gate at 1: both match.
+        let parser = build_brands(&[r"SM-A\d+", r"(?<!Galaxy )SM-A525"]);
+        assert_eq!(parser.fancy_brands.len(), 1);
+        assert_eq!(brand(&parser, ua), Some(0));
+
+        // Swapped, the fancy gate is the earlier entry and wins.
+        let parser = build_brands(&[r"(?<!Galaxy )SM-A525", r"SM-A\d+"]);
+        assert_eq!(brand(&parser, ua), Some(0));
+
+        // A later standard match does not hide an earlier fancy miss.
+        let parser = build_brands(&[r"(?<!Android 13; )SM-A525", r"SM-A\d+"]);
+        assert_eq!(brand(&parser, ua), Some(1));
+    }
+}
@@ -1,6 +1,7 @@
 use super::db;
 use super::types::{ClientType, DeviceType};
 use indexmap::IndexMap;
+use std::sync::Arc;
 
 // ---------------------------------------------------------------------------
 // Internal data structs carried inside CompiledParser<T>
@@ -31,13 +32,17 @@ pub(crate) struct EngineData {
 }
 
 pub(crate) struct DeviceBrandData {
-    pub brand: String,
+    /// Interned: shared with every other occurrence of the brand name.
+    pub brand: Arc<str>,
     pub model_template: Option<String>,
+    /// Brand-level `device:` from the YAML; `None` means use the file default.
     pub device_type: Option<DeviceType>,
 }
 
 pub(crate) struct DeviceModelData {
-    pub brand: Option<String>,
+    /// Interned model-level `brand:` override.  An empty `brand: ''` in the
+    /// YAML keeps the brand-level name and is stored as `None`.
+    pub brand: Option<Arc<str>>,
     pub model_template: Option<String>,
     pub device_type: Option<DeviceType>,
 }
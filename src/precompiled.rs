@@ -0,0 +1,148 @@
+//! Dataset tables generated by `build.rs` when the `precompiled` feature is
+//! enabled.  Rows mirror the `db` structs with `&'static str` fields so the
+//! whole dataset lives in static memory and needs no YAML parsing.
+
+use super::dataset::Dataset;
+use super::db;
+
+pub(crate) struct BotRow {
+    pub regex: &'static str,
+    pub name: &'static str,
+    pub category: Option<&'static str>,
+    pub url: Option<&'static str>,
+    pub producer: Option<(Option<&'static str>, Option<&'static str>)>,
+}
+
+pub(crate) struct OsRow {
+    pub regex: &'static str,
+    pub name: &'static str,
+    pub version: Option<&'static str>,
+}
+
+pub(crate) struct ClientRow {
+    pub regex: &'static str,
+    pub name: &'static str,
+    pub version: Option<&'static str>,
+    /// `(default, versions)` of the entry's `engine:` block.
+    pub engine: Option<EngineRefRow>,
+}
+
+pub(crate) type EngineRefRow = (
+    Option<&'static str>,
+    Option<&'static [(&'static str, &'static str)]>,
+);
+
+pub(crate) struct DeviceBrandRow {
+    pub brand: &'static str,
+    pub regex: Option<&'static str>,
+    pub device: Option<&'static str>,
+    pub model: Option<&'static str>,
+    pub models: Option<&'static [ModelRow]>,
+}
+
+pub(crate) struct ModelRow {
+    pub regex: &'static str,
+    pub model: Option<&'static str>,
+    pub device: Option<&'static str>,
+    pub brand: Option<&'static str>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/precompiled.rs"));
+
+fn string(s: &str) -> String {
+    s.to_string()
+}
+
+fn clients(rows: &[ClientRow]) -> Vec<db::BrowserEntry> {
+    rows.iter()
+        .map(|row| db::BrowserEntry {
+            regex: string(row.regex),
+            name: string(row.name),
+            version: row.version.map(string),
+            engine: row.engine.map(|(default, versions)| db::EngineRef {
+                default: default.map(string),
+                versions: versions
+                    .map(|v| v.iter().map(|&(k, n)| (string(k), string(n))).collect()),
+            }),
+        })
+        .collect()
+}
+
+fn hints(rows: &[(&str, &str)]) -> db::HintMap {
+    rows.iter().map(|&(k, v)| (string(k), string(v))).collect()
+}
+
+/// Convert the generated tables into a [`Dataset`].
+pub(crate) fn dataset() -> Dataset {
+    Dataset {
+        bots: BOTS
+            .iter()
+            .map(|row| db::BotEntry {
+                regex: string(row.regex),
+                name: string(row.name),
+                category: row.category.map(string),
+                url: row.url.map(string),
+                producer: row.producer.map(|(name, url)| db::BotProducer {
+                    name: name.map(string),
+                    url: url.map(string),
+                }),
+            })
+            .collect(),
+        oss: OSS
+            .iter()
+            .map(|row| db::OsEntry {
+                regex: string(row.regex),
+                name: string(row.name),
+                version: row.version.map(string),
+            })
+            .collect(),
+        browsers: clients(BROWSERS),
+        feed_readers: clients(FEED_READERS),
+        mobile_apps: clients(MOBILE_APPS),
+        libraries: clients(LIBRARIES),
+        media_players: clients(MEDIA_PLAYERS),
+        pims: clients(PIMS),
+        engines: ENGINES
+            .iter()
+            .map(|&(regex, name)| db::EngineEntry {
+                regex: string(regex),
+                name: string(name),
+            })
+            .collect(),
+        vendor_fragments: VENDOR_FRAGMENTS
+            .iter()
+            .map(|&(brand, patterns)| (string(brand), patterns.iter().map(|p| string(p)).collect()))
+            .collect(),
+        app_hints: hints(APP_HINTS),
+        browser_hints: hints(BROWSER_HINTS),
+        devices: DEVICES
+            .iter()
+            .map(|&(file, brands)| {
+                let brands = brands
+                    .iter()
+                    .map(|row| {
+                        let entry = db::DeviceBrandEntry {
+                            regex: row.regex.map(string),
+                            device: row.device.map(string),
+                            model: row.model.map(string),
+                            models: row.models.map(|models| {
+                                models
+                                    .iter()
+                                    .map(|m| db::ModelEntry {
+                                        regex: string(m.regex),
+                                        model: m.model.map(string),
+                                        device: m.device.map(string),
+                                        brand: m.brand.map(string),
+                                    })
+                                    .collect()
+                            }),
+                        };
+                        (string(row.brand), entry)
+                    })
+                    .collect();
+                (file, brands)
+            })
+            .collect(),
+        version: DATASET_VERSION.map(string),
+    }
+}
@@ -0,0 +1,46 @@
+use super::types::{ClientHints, Detection};
+use std::fmt;
+use std::sync::Arc;
+
+/// Built-in step of [`parse_with_hints`](crate::DeviceDetector::parse_with_hints)
+/// after which a [`DetectionStage`] runs.  Steps run in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PipelineStage {
+    /// Bot detection.  A stage here can clear `bot` to keep a UA from
+    /// short-circuiting the rest of the pipeline.
+    Bot,
+    /// OS detection, including the platform hint and version inference.
+    Os,
+    /// Client detection, including the `X-Requested-With` override.
+    Client,
+    /// Device file matching, before any device-type heuristic.
+    Device,
+    /// The device-type heuristics; the detection is complete.
+    Heuristics,
+}
+
+/// A custom step inserted into the detection pipeline, registered with
+/// [`DeviceDetectorBuilder::with_stage`](crate::DeviceDetectorBuilder::with_stage).
+///
+/// The stage sees the detection as far as the pipeline has got: fields of
+/// steps that have not run yet are `None`, and anything written to them is
+/// overwritten when those steps run.  Later built-in steps do read what the
+/// stage left in earlier fields (e.g. the device heuristics read the OS).
+/// When a bot short-circuits detection only [`PipelineStage::Bot`] stages
+/// run.  Stages placed after the same step run in registration order.
+pub trait DetectionStage: Send + Sync {
+    /// Built-in step this stage runs after.
+    fn after(&self) -> PipelineStage;
+
+    /// Inspect or rewrite the in-progress detection.
+    fn run<'a>(&self, ua: &'a str, hints: Option<&ClientHints>, detection: &mut Detection<'a>);
+}
+
+#[derive(Clone)]
+pub(crate) struct Stage(pub(crate) Arc<dyn DetectionStage>);
+
+impl fmt::Debug for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Stage({:?})", self.0.after())
+    }
+}
@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 
+use crate::helpers::owned;
 use crate::parser::Captures;
 
 /// Replace `$1`, `$2`, ... in `template` with capture groups from the regex
@@ -36,7 +37,7 @@ pub(crate) fn substitute<'a>(template: &'a str, captures: &Captures) -> Cow<'a,
         .trim_end_matches(|c: char| c.is_whitespace() || c == '.')
         .len();
     result.truncate(trimmed_len);
-    Cow::Owned(result)
+    owned(result)
 }
 
 #[cfg(test)]
@@ -0,0 +1,16 @@
+//! Per-thread counters behind the `timing` feature, read around a parse by
+//! [`DeviceDetector::parse_timed`](crate::DeviceDetector::parse_timed).
+
+use std::cell::Cell;
+
+thread_local! {
+    static OWNED_ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+pub(crate) fn record_owned() {
+    OWNED_ALLOCATIONS.with(|n| n.set(n.get() + 1));
+}
+
+pub(crate) fn owned_allocations() -> usize {
+    OWNED_ALLOCATIONS.with(Cell::get)
+}
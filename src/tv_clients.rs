@@ -0,0 +1,24 @@
+/// Clients that only run on TVs and set-top boxes, so their name alone
+/// classifies the device as a TV (Matomo's `DeviceDetector.php` list, plus
+/// newer Android TV browsers).
+///
+/// Kept sorted; new Android TV browsers appear regularly, so add them here
+/// rather than in `parse_with_hints`.
+pub(crate) const TV_CLIENTS: &[&str] = &[
+    "Crow Browser",
+    "Espial TV Browser",
+    "Kidoz TV",
+    "Kylo",
+    "LUJO TV Browser",
+    "LogicUI TV Browser",
+    "Open TV Browser",
+    "Opera Devices",
+    "Puffin TV",
+    "QJY TV Browser",
+    "Quick Search TV",
+    "Redline",
+    "Seraphic Sraf",
+    "TV Bro",
+    "TiviMate",
+    "Vewd Browser",
+];
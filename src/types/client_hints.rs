@@ -1,3 +1,5 @@
+use std::fmt::Write;
+
 /// Client hints extracted from HTTP headers (e.g. `X-Requested-With`,
 /// `Sec-CH-UA-Mobile`, `Sec-CH-UA-Model`).
 #[derive(Debug, Clone, Default)]
@@ -8,4 +10,118 @@ pub struct ClientHints {
     pub model: Option<String>,
     /// Mobile flag from `Sec-CH-UA-Mobile` (`?1` → true).
     pub mobile: Option<bool>,
+    /// OS name from `Sec-CH-UA-Platform` (e.g. `Windows`, `macOS`).
+    pub platform: Option<String>,
+    /// OS version from `Sec-CH-UA-Platform-Version`.
+    pub platform_version: Option<String>,
+    /// CPU architecture from `Sec-CH-UA-Arch` (e.g. `arm`, `x86`).
+    pub architecture: Option<String>,
+    /// Architecture bitness from `Sec-CH-UA-Bitness` (e.g. `64`).
+    pub bitness: Option<String>,
+    /// `(brand, version)` pairs from `Sec-CH-UA`, greasing entries included.
+    pub brands: Vec<(String, String)>,
+    /// Browser version from the legacy `Sec-CH-UA-Full-Version` header,
+    /// which early Chromium releases sent before `Sec-CH-UA-Full-Version-List`.
+    pub full_version: Option<String>,
+    /// Values of `Sec-CH-UA-Form-Factors` (e.g. `Desktop`, `Tablet`).
+    pub form_factors: Vec<String>,
+}
+
+impl ClientHints {
+    /// [`brands`](Self::brands) without Chromium's greasing entries
+    /// (`"Not.A/Brand"`, `"Not;A=Brand"`, `" Not A;Brand"`, ...).
+    pub fn real_brands(&self) -> Vec<(&str, &str)> {
+        self.brands
+            .iter()
+            .filter(|(brand, _)| !is_greasing_brand(brand))
+            .map(|(brand, version)| (brand.as_str(), version.as_str()))
+            .collect()
+    }
+
+    /// One `Header: value` line per populated field, in the order of
+    /// [`HINT_HEADERS`], showing exactly what was parsed from the headers.
+    /// Diagnostic output; keep it off the hot path.
+    pub fn debug_summary(&self) -> String {
+        let mut summary = String::new();
+        for (header, value) in self.populated() {
+            if !summary.is_empty() {
+                summary.push('\n');
+            }
+            let _ = write!(summary, "{}: {}", header, value);
+        }
+        summary
+    }
+
+    /// `(header, value)` for every populated field.
+    pub(crate) fn populated(&self) -> impl Iterator<Item = (&'static str, String)> + '_ {
+        HINT_HEADERS
+            .iter()
+            .filter_map(|&header| self.header_value(header).map(|v| (header, v)))
+    }
+
+    /// A copy with the field read from `header` cleared.
+    pub(crate) fn without(&self, header: &str) -> ClientHints {
+        let mut hints = self.clone();
+        match header {
+            "X-Requested-With" => hints.x_requested_with = None,
+            "Sec-CH-UA-Model" => hints.model = None,
+            "Sec-CH-UA-Mobile" => hints.mobile = None,
+            "Sec-CH-UA-Platform" => hints.platform = None,
+            "Sec-CH-UA-Platform-Version" => hints.platform_version = None,
+            "Sec-CH-UA-Arch" => hints.architecture = None,
+            "Sec-CH-UA-Bitness" => hints.bitness = None,
+            "Sec-CH-UA" => hints.brands.clear(),
+            "Sec-CH-UA-Full-Version" => hints.full_version = None,
+            "Sec-CH-UA-Form-Factors" => hints.form_factors.clear(),
+            _ => {}
+        }
+        hints
+    }
+
+    fn header_value(&self, header: &str) -> Option<String> {
+        match header {
+            "X-Requested-With" => self.x_requested_with.clone(),
+            "Sec-CH-UA-Model" => self.model.clone(),
+            "Sec-CH-UA-Mobile" => self.mobile.map(|m| if m { "?1" } else { "?0" }.to_string()),
+            "Sec-CH-UA-Platform" => self.platform.clone(),
+            "Sec-CH-UA-Platform-Version" => self.platform_version.clone(),
+            "Sec-CH-UA-Arch" => self.architecture.clone(),
+            "Sec-CH-UA-Bitness" => self.bitness.clone(),
+            "Sec-CH-UA" if !self.brands.is_empty() => Some(
+                self.brands
+                    .iter()
+                    .map(|(brand, version)| format!("\"{}\";v=\"{}\"", brand, version))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            "Sec-CH-UA-Full-Version" => self.full_version.clone(),
+            "Sec-CH-UA-Form-Factors" if !self.form_factors.is_empty() => {
+                Some(self.form_factors.join(", "))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Headers backing the [`ClientHints`] fields, in field order.
+pub const HINT_HEADERS: [&str; 10] = [
+    "X-Requested-With",
+    "Sec-CH-UA-Model",
+    "Sec-CH-UA-Mobile",
+    "Sec-CH-UA-Platform",
+    "Sec-CH-UA-Platform-Version",
+    "Sec-CH-UA-Arch",
+    "Sec-CH-UA-Bitness",
+    "Sec-CH-UA",
+    "Sec-CH-UA-Full-Version",
+    "Sec-CH-UA-Form-Factors",
+];
+
+/// Chromium builds its greasing brand from "Not", "A" and "Brand" joined by
+/// characters it rotates between releases, so compare the letters only.
+fn is_greasing_brand(brand: &str) -> bool {
+    let letters = brand.chars().filter(|c| c.is_ascii_alphanumeric());
+    letters
+        .map(|c| c.to_ascii_lowercase())
+        .eq("notabrand".chars())
 }
@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ClientType {
     Browser,
@@ -6,9 +8,25 @@ pub enum ClientType {
     Pim,
     Library,
     MediaPlayer,
+    /// Userscript / extension runtimes (Tampermonkey, Greasemonkey, ...).
+    /// Not part of Matomo's dataset; detected from a built-in list.
+    BrowserExtension,
 }
 
 impl ClientType {
+    /// Every client type, in detection order.
+    pub fn all() -> &'static [ClientType] {
+        &[
+            Self::BrowserExtension,
+            Self::Browser,
+            Self::FeedReader,
+            Self::MobileApp,
+            Self::Library,
+            Self::MediaPlayer,
+            Self::Pim,
+        ]
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::Browser => "browser",
@@ -17,6 +35,36 @@ impl ClientType {
             Self::Pim => "pim",
             Self::Library => "library",
             Self::MediaPlayer => "mediaplayer",
+            Self::BrowserExtension => "browser extension",
+        }
+    }
+}
+
+impl FromStr for ClientType {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|t| t.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| crate::Error::UnknownClientType(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_round_trips_all() {
+        for &t in ClientType::all() {
+            assert_eq!(t.as_str().parse::<ClientType>().unwrap(), t);
         }
     }
+
+    #[test]
+    fn from_str_rejects_unknown() {
+        assert!("spaceship".parse::<ClientType>().is_err());
+    }
 }
@@ -0,0 +1,13 @@
+use std::path::PathBuf;
+
+/// A problem found by [`DeviceDetector::validate_dir`](crate::DeviceDetector::validate_dir).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatasetIssue {
+    /// YAML file the issue was found in.
+    pub file: PathBuf,
+    /// Entry the regex belongs to (e.g. bot/OS/client name, `Brand` or
+    /// `Brand / model`).  Empty for file-level issues.
+    pub entry: String,
+    /// Human-readable description (I/O, YAML or regex compile error).
+    pub message: String,
+}
@@ -1,9 +1,16 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Detection<'a> {
     pub bot: Option<Bot<'a>>,
     pub os: Option<Os<'a>>,
     pub client: Option<Client<'a>>,
     pub device: Option<Device<'a>>,
+    /// `true` for Android-family UAs carrying the `; wv)` WebView marker.
+    pub android_webview: bool,
+    /// `true` for UAs carrying an Android-on-PC marker (Android-x86,
+    /// BlueStacks).
+    pub emulator: bool,
+    /// Dataset entries that produced each stage.
+    pub rule_ids: super::RuleIds,
 }
 
 impl<'a> Detection<'a> {
@@ -22,6 +29,73 @@ impl<'a> Detection<'a> {
     pub fn device(&self) -> Option<&Device<'a>> {
         self.device.as_ref()
     }
+    pub fn is_android_webview(&self) -> bool {
+        self.android_webview
+    }
+    pub fn is_emulator(&self) -> bool {
+        self.emulator
+    }
+    pub fn rule_ids(&self) -> &super::RuleIds {
+        &self.rule_ids
+    }
+
+    /// `true` when the detected OS is `name` (case-insensitive) at `version`
+    /// or later, compared with [`compare_versions`](crate::compare_versions).
+    /// `false` when no OS was detected or its version is unknown.
+    pub fn os_at_least(&self, name: &str, version: &str) -> bool {
+        self.os.as_ref().is_some_and(|os| {
+            os.name.eq_ignore_ascii_case(name)
+                && !os.version.is_empty()
+                && crate::compare_versions(&os.version, version).is_ge()
+        })
+    }
+
+    /// `true` for fetchers that build link previews and social embeds: any
+    /// bot in the "Social Media Agent" category, plus a curated set of
+    /// preview bots (Twitterbot, LinkedInBot, Discordbot, ...) whatever
+    /// their category.
+    pub fn is_link_preview_bot(&self) -> bool {
+        self.bot
+            .as_ref()
+            .is_some_and(|b| is_link_preview_bot(&b.name, b.category))
+    }
+
+    /// Stable 64-bit hash of the detected bot name, OS name and version,
+    /// client type, name and version, and device type, brand and model, for
+    /// dedup and sharding.  Engines, platform and the UA itself are left out.
+    ///
+    /// SeaHash over the field bytes, so the value is the same across runs,
+    /// platforms and releases of this crate.
+    pub fn fingerprint(&self) -> u64 {
+        let os = self.os.as_ref();
+        let client = self.client.as_ref();
+        let device = self.device.as_ref();
+        fingerprint([
+            self.bot.as_ref().map(|b| b.name.as_ref()),
+            os.map(|o| o.name.as_ref()),
+            os.map(|o| o.version.as_ref()),
+            client.map(|c| c.kind.as_str()),
+            client.map(|c| c.name.as_ref()),
+            client.map(|c| c.version.as_ref()),
+            device.and_then(|d| d.kind).map(|k| k.as_str()),
+            device.map(|d| d.brand.as_ref()),
+            device.map(|d| d.model.as_ref()),
+        ])
+    }
+
+    /// `true` when both detections describe the same class of device and
+    /// software: same bot name, OS name, client name and type, device type
+    /// and brand.  Versions, models and engines are ignored, so version
+    /// churn of one browser on one device counts as the same class.
+    pub fn same_class_as(&self, other: &Detection<'_>) -> bool {
+        self.bot.as_ref().map(|b| b.name.as_ref()) == other.bot.as_ref().map(|b| b.name.as_ref())
+            && self.os.as_ref().map(|o| o.name.as_ref())
+                == other.os.as_ref().map(|o| o.name.as_ref())
+            && self.client.as_ref().map(|c| (c.kind, c.name.as_ref()))
+                == other.client.as_ref().map(|c| (c.kind, c.name.as_ref()))
+            && self.device.as_ref().map(|d| (d.kind, d.brand.as_ref()))
+                == other.device.as_ref().map(|d| (d.kind, d.brand.as_ref()))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -42,6 +116,11 @@ pub struct BotProducer<'a> {
 pub struct Os<'a> {
     pub name: ::std::borrow::Cow<'a, str>,
     pub version: ::std::borrow::Cow<'a, str>,
+    /// CPU platform (`ARM`, `x64`, `x86`, ...), empty when unknown.
+    pub platform: ::std::borrow::Cow<'a, str>,
+    /// `true` when `version` was not in the UA but inferred from other
+    /// tokens (e.g. an Android version from the Chrome version).
+    pub version_inferred: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -51,6 +130,14 @@ pub struct Client<'a> {
     pub version: ::std::borrow::Cow<'a, str>,
     pub engine: ::std::borrow::Cow<'a, str>,
     pub engine_version: ::std::borrow::Cow<'a, str>,
+    /// Browsing mode signalled by a UA token (`"reader"`, `"private"`), or
+    /// `"compatibility"` for IE's compatibility view; `None` for the usual
+    /// case of no such token.
+    pub mode: Option<&'static str>,
+    /// Pre-release channel of a browser (`"beta"`, `"dev"`, `"nightly"`,
+    /// `"canary"`) recognised from its UA tokens; `None` for stable builds
+    /// and for UAs that don't say.
+    pub channel: Option<&'static str>,
 }
 
 #[derive(Debug, Clone)]
@@ -59,3 +146,140 @@ pub struct Device<'a> {
     pub brand: ::std::borrow::Cow<'a, str>,
     pub model: ::std::borrow::Cow<'a, str>,
 }
+
+impl Device<'_> {
+    /// Screen resolution class of the detected model, from a small table of
+    /// popular devices; `None` for anything not in it.
+    pub fn resolution_class(&self) -> Option<super::ResolutionClass> {
+        super::resolution_class::resolution_class(&self.brand, &self.model)
+    }
+}
+
+impl Os<'_> {
+    /// Normalized identifier for storage keys and joins: lowercase, with
+    /// each run of spaces and punctuation collapsed to one `-` ("Windows RT"
+    /// → `windows-rt`, "GNU/Linux" → `gnu-linux`).
+    pub fn key(&self) -> String {
+        os_key(&self.name)
+    }
+}
+
+pub(super) fn os_key(name: &str) -> String {
+    let mut key = String::with_capacity(name.len());
+    for word in name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        if !key.is_empty() {
+            key.push('-');
+        }
+        key.extend(word.chars().flat_map(char::to_lowercase));
+    }
+    key
+}
+
+/// Engine family of well-known browsers, for clients whose entry names no
+/// engine.
+const BROWSER_ENGINES: &[(&str, &str)] = &[
+    ("Safari", "WebKit"),
+    ("Mobile Safari", "WebKit"),
+    ("Firefox", "Gecko"),
+    ("Firefox Mobile", "Gecko"),
+    ("Chrome", "Blink"),
+    ("Chrome Mobile", "Blink"),
+    ("Chromium", "Blink"),
+    ("Microsoft Edge", "Blink"),
+    ("Opera", "Blink"),
+    ("Opera GX", "Blink"),
+    ("Brave", "Blink"),
+    ("Samsung Browser", "Blink"),
+    ("Internet Explorer", "Trident"),
+    ("IE Mobile", "Trident"),
+];
+
+pub(super) fn engine_family<'a>(name: &str, engine: &'a str) -> Option<&'a str> {
+    if !engine.is_empty() {
+        return Some(engine);
+    }
+    BROWSER_ENGINES
+        .iter()
+        .find(|(browser, _)| *browser == name)
+        .map(|(_, engine)| *engine)
+}
+
+/// Link-preview bot names, lowercase with punctuation and spaces removed so
+/// that UA tokens and dataset names compare equal ("facebookexternalhit" vs
+/// "Facebook External Hit").
+const LINK_PREVIEW_BOTS: &[&str] = &[
+    "twitterbot",
+    "facebookexternalhit",
+    "linkedinbot",
+    "discordbot",
+    "telegrambot",
+    "whatsapp",
+];
+
+pub(super) fn fingerprint(fields: [Option<&str>; 9]) -> u64 {
+    use std::hash::Hasher;
+
+    let mut hasher = seahash::SeaHasher::new();
+    for field in fields {
+        // Length-prefixed so text can't shift between fields; a missing
+        // field hashes differently from an empty one.
+        match field {
+            Some(s) => {
+                hasher.write(&(s.len() as u64).to_le_bytes());
+                hasher.write(s.as_bytes());
+            }
+            None => hasher.write(&u64::MAX.to_le_bytes()),
+        }
+    }
+    hasher.finish()
+}
+
+pub(super) fn is_link_preview_bot(name: &str, category: Option<&str>) -> bool {
+    if category == Some("Social Media Agent") {
+        return true;
+    }
+    let name: String = name
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    LINK_PREVIEW_BOTS.contains(&name.as_str())
+}
+
+/// Model-name fragments of foldable and dual-screen devices, lowercase.
+const FOLDABLE_MODELS: &[&str] = &[
+    "galaxy fold",
+    "galaxy z fold",
+    "galaxy z flip",
+    "surface duo",
+    "pixel fold",
+    "razr",
+    "mate x",
+    "magic v",
+    "find n",
+    "mix fold",
+    "mix flip",
+    "x fold",
+];
+
+impl Client<'_> {
+    /// The client's engine, or when its entry names none, the engine family
+    /// of a well-known browser of that name (Safari → WebKit, Firefox →
+    /// Gecko, Chrome/Edge/Opera → Blink, IE → Trident).  `None` for other
+    /// clients without an engine.
+    pub fn inferred_engine_family(&self) -> Option<&str> {
+        engine_family(&self.name, &self.engine)
+    }
+}
+
+impl Device<'_> {
+    /// `true` for foldable and dual-screen devices (Galaxy Z Fold/Flip,
+    /// Surface Duo, Pixel Fold, ...), judged from the model name.
+    pub fn is_foldable(&self) -> bool {
+        let model = self.model.to_ascii_lowercase();
+        FOLDABLE_MODELS.iter().any(|m| model.contains(m))
+    }
+}
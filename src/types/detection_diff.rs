@@ -0,0 +1,126 @@
+use std::fmt;
+
+use super::Detection;
+
+/// A field compared by [`DetectionDiff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DetectionField {
+    Bot,
+    OsName,
+    OsVersion,
+    ClientName,
+    ClientVersion,
+    DeviceType,
+    DeviceBrand,
+    DeviceModel,
+}
+
+impl DetectionField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bot => "bot",
+            Self::OsName => "os.name",
+            Self::OsVersion => "os.version",
+            Self::ClientName => "client.name",
+            Self::ClientVersion => "client.version",
+            Self::DeviceType => "device.type",
+            Self::DeviceBrand => "device.brand",
+            Self::DeviceModel => "device.model",
+        }
+    }
+}
+
+/// One field that differs between two detections.  A missing stage reads as
+/// an empty string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: DetectionField,
+    pub old: String,
+    pub new: String,
+}
+
+/// Field-by-field difference between two detections of the same UA, e.g.
+/// from detectors built on two dataset versions.
+///
+/// `Display` renders one `field: "old" -> "new"` line per change.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DetectionDiff {
+    pub changes: Vec<FieldChange>,
+}
+
+impl DetectionDiff {
+    pub fn compare(old: &Detection<'_>, new: &Detection<'_>) -> Self {
+        let fields = |d: &Detection<'_>| -> [(DetectionField, String); 8] {
+            let os = d.os.as_ref();
+            let client = d.client.as_ref();
+            let device = d.device.as_ref();
+            [
+                (
+                    DetectionField::Bot,
+                    d.bot.as_ref().map(|b| b.name.to_string()),
+                ),
+                (DetectionField::OsName, os.map(|o| o.name.to_string())),
+                (DetectionField::OsVersion, os.map(|o| o.version.to_string())),
+                (
+                    DetectionField::ClientName,
+                    client.map(|c| c.name.to_string()),
+                ),
+                (
+                    DetectionField::ClientVersion,
+                    client.map(|c| c.version.to_string()),
+                ),
+                (
+                    DetectionField::DeviceType,
+                    device.and_then(|d| d.kind).map(|k| k.as_str().to_string()),
+                ),
+                (
+                    DetectionField::DeviceBrand,
+                    device.map(|d| d.brand.to_string()),
+                ),
+                (
+                    DetectionField::DeviceModel,
+                    device.map(|d| d.model.to_string()),
+                ),
+            ]
+            .map(|(field, value)| (field, value.unwrap_or_default()))
+        };
+
+        let changes = fields(old)
+            .into_iter()
+            .zip(fields(new))
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((field, old), (_, new))| FieldChange { field, old, new })
+            .collect();
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The changed fields, in [`DetectionField`] order.
+    pub fn fields(&self) -> impl Iterator<Item = DetectionField> + '_ {
+        self.changes.iter().map(|c| c.field)
+    }
+}
+
+impl fmt::Display for DetectionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return f.write_str("no changes");
+        }
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(
+                f,
+                "{}: {:?} -> {:?}",
+                change.field.as_str(),
+                change.old,
+                change.new
+            )?;
+        }
+        Ok(())
+    }
+}
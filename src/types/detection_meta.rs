@@ -0,0 +1,30 @@
+/// Numeric fingerprint of a detection for compact structured logging.
+///
+/// Each stage holds the zero-based index of its dataset entry in the file
+/// named by the matching [`RuleId`](super::RuleId) (the brand index for
+/// devices); `None` when nothing matched or a hint or heuristic supplied
+/// the value.  Unlike [`Detection`](super::Detection) it borrows nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DetectionMeta {
+    pub bot: Option<usize>,
+    pub os: Option<usize>,
+    pub client: Option<usize>,
+    pub device: Option<usize>,
+    /// Model position within the device brand.
+    pub device_model: Option<usize>,
+    /// Client hints were passed to the parse.
+    pub hints_used: bool,
+}
+
+impl DetectionMeta {
+    pub(crate) fn new(rule_ids: &super::RuleIds, hints_used: bool) -> Self {
+        Self {
+            bot: rule_ids.bot.map(|r| r.index),
+            os: rule_ids.os.map(|r| r.index),
+            client: rule_ids.client.map(|r| r.index),
+            device: rule_ids.device.map(|r| r.index),
+            device_model: rule_ids.device.and_then(|r| r.model),
+            hints_used,
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use super::{Bot, BotProducer, Client, ClientType, Detection, Device, DeviceType, Os, RuleIds};
+
+/// Owned counterpart of [`Detection`], independent of the detector and the
+/// UA string.  Returned by [`DeviceDetector::parse_owned`](crate::DeviceDetector::parse_owned)
+/// and handed to post hooks, which may edit it freely.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DetectionOwned {
+    pub bot: Option<BotOwned>,
+    pub os: Option<OsOwned>,
+    pub client: Option<ClientOwned>,
+    pub device: Option<DeviceOwned>,
+    pub android_webview: bool,
+    pub emulator: bool,
+    pub rule_ids: RuleIds,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotOwned {
+    pub name: String,
+    pub category: Option<String>,
+    pub url: Option<String>,
+    pub producer: Option<BotProducerOwned>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotProducerOwned {
+    pub name: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OsOwned {
+    pub name: String,
+    pub version: String,
+    pub platform: String,
+    pub version_inferred: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientOwned {
+    pub kind: ClientType,
+    pub name: String,
+    pub version: String,
+    pub engine: String,
+    pub engine_version: String,
+    pub mode: Option<&'static str>,
+    pub channel: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceOwned {
+    pub kind: Option<DeviceType>,
+    pub brand: String,
+    pub model: String,
+}
+
+impl OsOwned {
+    /// See [`Os::key`].
+    pub fn key(&self) -> String {
+        super::detection::os_key(&self.name)
+    }
+}
+
+impl ClientOwned {
+    /// See [`Client::inferred_engine_family`].
+    pub fn inferred_engine_family(&self) -> Option<&str> {
+        super::detection::engine_family(&self.name, &self.engine)
+    }
+}
+
+impl DeviceOwned {
+    /// See [`Device::resolution_class`].
+    pub fn resolution_class(&self) -> Option<super::ResolutionClass> {
+        super::resolution_class::resolution_class(&self.brand, &self.model)
+    }
+}
+
+impl DetectionOwned {
+    pub fn is_bot(&self) -> bool {
+        self.bot.is_some()
+    }
+
+    /// See [`Detection::is_link_preview_bot`].
+    pub fn is_link_preview_bot(&self) -> bool {
+        self.bot
+            .as_ref()
+            .is_some_and(|b| super::detection::is_link_preview_bot(&b.name, b.category.as_deref()))
+    }
+
+    /// See [`Detection::fingerprint`]; equal for a detection and its owned
+    /// copy.
+    pub fn fingerprint(&self) -> u64 {
+        let os = self.os.as_ref();
+        let client = self.client.as_ref();
+        let device = self.device.as_ref();
+        super::detection::fingerprint([
+            self.bot.as_ref().map(|b| b.name.as_str()),
+            os.map(|o| o.name.as_str()),
+            os.map(|o| o.version.as_str()),
+            client.map(|c| c.kind.as_str()),
+            client.map(|c| c.name.as_str()),
+            client.map(|c| c.version.as_str()),
+            device.and_then(|d| d.kind).map(|k| k.as_str()),
+            device.map(|d| d.brand.as_str()),
+            device.map(|d| d.model.as_str()),
+        ])
+    }
+}
+
+impl Detection<'_> {
+    /// Copy every borrowed field into a [`DetectionOwned`].
+    pub fn into_owned(self) -> DetectionOwned {
+        DetectionOwned {
+            bot: self.bot.map(BotOwned::from),
+            os: self.os.map(OsOwned::from),
+            client: self.client.map(ClientOwned::from),
+            device: self.device.map(DeviceOwned::from),
+            android_webview: self.android_webview,
+            emulator: self.emulator,
+            rule_ids: self.rule_ids,
+        }
+    }
+}
+
+impl From<Bot<'_>> for BotOwned {
+    fn from(bot: Bot<'_>) -> Self {
+        Self {
+            name: bot.name.into_owned(),
+            category: bot.category.map(str::to_string),
+            url: bot.url.map(str::to_string),
+            producer: bot.producer.map(BotProducerOwned::from),
+        }
+    }
+}
+
+impl From<BotProducer<'_>> for BotProducerOwned {
+    fn from(producer: BotProducer<'_>) -> Self {
+        Self {
+            name: producer.name.map(str::to_string),
+            url: producer.url.map(str::to_string),
+        }
+    }
+}
+
+impl From<Os<'_>> for OsOwned {
+    fn from(os: Os<'_>) -> Self {
+        Self {
+            name: os.name.into_owned(),
+            version: os.version.into_owned(),
+            platform: os.platform.into_owned(),
+            version_inferred: os.version_inferred,
+        }
+    }
+}
+
+impl From<Client<'_>> for ClientOwned {
+    fn from(client: Client<'_>) -> Self {
+        Self {
+            kind: client.kind,
+            name: client.name.into_owned(),
+            version: client.version.into_owned(),
+            engine: client.engine.into_owned(),
+            engine_version: client.engine_version.into_owned(),
+            mode: client.mode,
+            channel: client.channel,
+        }
+    }
+}
+
+impl From<Device<'_>> for DeviceOwned {
+    fn from(device: Device<'_>) -> Self {
+        Self {
+            kind: device.kind,
+            brand: device.brand.into_owned(),
+            model: device.model.into_owned(),
+        }
+    }
+}
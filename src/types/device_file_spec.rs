@@ -0,0 +1,135 @@
+use super::DeviceType;
+
+/// When a device file's parser runs, mirroring the prefilters of Matomo's
+/// PHP device parsers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DevicePrefilterKind {
+    /// The UA must match this regex (compiled as is, without the boundary
+    /// prefix), e.g. `HbbTV/` for televisions.yml.
+    Specific(String),
+    /// The UA must match one of the file's brand regexes.
+    Overall,
+    /// Always run.
+    None,
+}
+
+/// How one file under `regexes/device/` is loaded and matched.
+///
+/// Register overrides or extra files with
+/// [`DeviceDetectorBuilder::device_file`](crate::DeviceDetectorBuilder::device_file).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceFileSpec {
+    /// File name under `regexes/device/`.
+    pub file: &'static str,
+    /// Type for matches whose model and brand entries don't set `device:`.
+    pub default_type: DeviceType,
+    pub prefilter: DevicePrefilterKind,
+    /// When the prefilter matches but no brand does, report a brandless
+    /// device of `default_type` instead of trying later files (Matomo's
+    /// HbbTv/ShellTv behaviour).
+    pub claims_type: bool,
+}
+
+impl DeviceFileSpec {
+    pub fn new(file: &'static str, default_type: DeviceType) -> Self {
+        Self {
+            file,
+            default_type,
+            prefilter: DevicePrefilterKind::Overall,
+            claims_type: false,
+        }
+    }
+
+    /// Matomo's device files, in the order their parsers run.
+    pub fn matomo_defaults() -> Vec<Self> {
+        DeviceFile::ALL.iter().map(|file| file.spec()).collect()
+    }
+}
+
+/// Matomo's device files.  Their names, types and prefilters are defined
+/// here only, so a misspelled or forgotten file is a compile error rather
+/// than a parser silently missing at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceFile {
+    ShellTv,
+    Televisions,
+    Consoles,
+    CarBrowsers,
+    Cameras,
+    PortableMediaPlayer,
+    Notebooks,
+    Mobiles,
+}
+
+impl DeviceFile {
+    /// Every file, in the order their parsers run.
+    pub const ALL: [Self; 8] = [
+        Self::ShellTv,
+        Self::Televisions,
+        Self::Consoles,
+        Self::CarBrowsers,
+        Self::Cameras,
+        Self::PortableMediaPlayer,
+        Self::Notebooks,
+        Self::Mobiles,
+    ];
+
+    /// File name under `regexes/device/`.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::ShellTv => "shell_tv.yml",
+            Self::Televisions => "televisions.yml",
+            Self::Consoles => "consoles.yml",
+            Self::CarBrowsers => "car_browsers.yml",
+            Self::Cameras => "cameras.yml",
+            Self::PortableMediaPlayer => "portable_media_player.yml",
+            Self::Notebooks => "notebooks.yml",
+            Self::Mobiles => "mobiles.yml",
+        }
+    }
+
+    /// Type for matches whose model and brand entries don't set `device:`.
+    pub fn default_type(self) -> DeviceType {
+        match self {
+            Self::ShellTv | Self::Televisions => DeviceType::Tv,
+            Self::Consoles => DeviceType::Console,
+            Self::CarBrowsers => DeviceType::CarBrowser,
+            Self::Cameras => DeviceType::Camera,
+            Self::PortableMediaPlayer => DeviceType::PortableMediaPlayer,
+            Self::Notebooks => DeviceType::Notebook,
+            Self::Mobiles => DeviceType::Smartphone,
+        }
+    }
+
+    /// When the file's parser runs (Matomo's per-parser prefilters).
+    pub fn prefilter(self) -> DevicePrefilterKind {
+        match self {
+            Self::ShellTv => {
+                DevicePrefilterKind::Specific(r"(?i)[a-z]+[ _]Shell[ _]\w{6}|tclwebkit".to_string())
+            }
+            Self::Televisions => {
+                DevicePrefilterKind::Specific(r"(?i)(?:HbbTV|SmartTvA)/".to_string())
+            }
+            Self::Notebooks => DevicePrefilterKind::Specific("FBMD/".to_string()),
+            Self::Mobiles => DevicePrefilterKind::None,
+            Self::Consoles | Self::CarBrowsers | Self::Cameras | Self::PortableMediaPlayer => {
+                DevicePrefilterKind::Overall
+            }
+        }
+    }
+
+    /// See [`DeviceFileSpec::claims_type`].
+    pub fn claims_type(self) -> bool {
+        matches!(self, Self::ShellTv | Self::Televisions)
+    }
+
+    /// The spec [`DeviceFileSpec::matomo_defaults`] uses for this file.
+    pub fn spec(self) -> DeviceFileSpec {
+        DeviceFileSpec {
+            file: self.file_name(),
+            default_type: self.default_type(),
+            prefilter: self.prefilter(),
+            claims_type: self.claims_type(),
+        }
+    }
+}
@@ -0,0 +1,13 @@
+/// How [`DeviceDetector`](crate::DeviceDetector) picks between device files
+/// when more than one matches a UA.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeviceSelection {
+    /// Stop at the first device file whose brand matches, in Matomo's file
+    /// order (TVs, consoles, ..., mobiles).  Matches Matomo.
+    #[default]
+    FirstMatch,
+    /// Run every device file and prefer a match where a model regex (not
+    /// just the brand) matched, then the longest matched model text.  Ties
+    /// keep file order.
+    MostSpecific,
+}
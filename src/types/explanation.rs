@@ -0,0 +1,13 @@
+/// The source YAML regexes behind a detection, for dataset debugging.
+///
+/// Patterns are reported as written in Matomo's files, without the boundary
+/// prefix and `(?i)` flag added at compile time.  A `None` field means no
+/// entry of that stage matched.
+#[derive(Debug, Clone, Default)]
+pub struct Explanation<'a> {
+    pub bot: Option<&'a str>,
+    pub os: Option<&'a str>,
+    pub client: Option<&'a str>,
+    pub device_brand: Option<&'a str>,
+    pub device_model: Option<&'a str>,
+}
@@ -1,9 +1,33 @@
 mod client_hints;
 mod client_type;
+mod dataset_issue;
 mod detection;
+mod detection_diff;
+mod detection_meta;
+mod detection_owned;
+mod device_file_spec;
+mod device_selection;
 mod device_type;
+mod explanation;
+mod resolution_class;
+mod rule_id;
+mod stats;
+#[cfg(feature = "timing")]
+mod timing_snapshot;
 
 pub use client_hints::*;
 pub use client_type::*;
+pub use dataset_issue::*;
 pub use detection::*;
+pub use detection_diff::*;
+pub use detection_meta::*;
+pub use detection_owned::*;
+pub use device_file_spec::*;
+pub use device_selection::*;
 pub use device_type::*;
+pub use explanation::*;
+pub use resolution_class::*;
+pub use rule_id::*;
+pub use stats::*;
+#[cfg(feature = "timing")]
+pub use timing_snapshot::*;
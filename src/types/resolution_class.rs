@@ -0,0 +1,46 @@
+/// Coarse screen resolution class, by the panel's shorter side: 720p and
+/// up is HD, 1080p full HD, 1440p QHD and 2160p 4K.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResolutionClass {
+    Hd,
+    FullHd,
+    Qhd,
+    Uhd4k,
+}
+
+impl ResolutionClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hd => "hd",
+            Self::FullHd => "fhd",
+            Self::Qhd => "qhd",
+            Self::Uhd4k => "4k",
+        }
+    }
+}
+
+/// Known `(brand, model, class)` triples, with brand and model as the
+/// dataset reports them.  A short hand-maintained list of popular devices,
+/// not a full catalog; add entries as reports need them.
+const RESOLUTION_CLASSES: &[(&str, &str, ResolutionClass)] = &[
+    ("Amazon", "Echo Show 8", ResolutionClass::Hd),
+    ("Amazon", "Fire TV Stick 4K", ResolutionClass::Uhd4k),
+    ("Apple", "iPhone 15", ResolutionClass::FullHd),
+    ("Apple", "iPhone 15 Pro Max", ResolutionClass::FullHd),
+    ("Google", "Pixel 7", ResolutionClass::FullHd),
+    ("Google", "Pixel 7 Pro", ResolutionClass::Qhd),
+    ("LG", "OLED55C1", ResolutionClass::Uhd4k),
+    ("LG", "OLED65C1", ResolutionClass::Uhd4k),
+    ("Samsung", "Galaxy Note 8", ResolutionClass::Qhd),
+    ("Samsung", "Galaxy Note 20 Ultra 5G", ResolutionClass::Qhd),
+    ("Samsung", "Galaxy S21 5G", ResolutionClass::FullHd),
+    ("Sony", "BRAVIA 4K GB", ResolutionClass::Uhd4k),
+    ("Xiaomi", "Mi Box 4", ResolutionClass::Uhd4k),
+];
+
+pub(super) fn resolution_class(brand: &str, model: &str) -> Option<ResolutionClass> {
+    RESOLUTION_CLASSES
+        .iter()
+        .find(|(b, m, _)| *b == brand && *m == model)
+        .map(|(.., class)| *class)
+}
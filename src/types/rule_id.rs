@@ -0,0 +1,29 @@
+/// Identifies the dataset entry behind one part of a detection.
+///
+/// Stable for a given dataset: the same UA always maps to the same id, so
+/// ids can be logged as an audit trail and resolved later against the
+/// files they name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RuleId {
+    /// Source file name (e.g. `browsers.yml`, `mobiles.yml`; names are
+    /// unique across `regexes/`), or `<builtin:browser_extensions>` /
+    /// `<builtin:http_libraries>` for the crate's own supplementary lists.
+    pub file: &'static str,
+    /// Zero-based entry position in the file; the brand position for device
+    /// files.
+    pub index: usize,
+    /// Zero-based model position within the brand, for device matches.
+    pub model: Option<usize>,
+}
+
+/// [`RuleId`]s for each stage of a [`Detection`](super::Detection).
+///
+/// A stage is `None` when nothing matched or when its result came from a
+/// client hint or heuristic rather than a dataset entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuleIds {
+    pub bot: Option<RuleId>,
+    pub os: Option<RuleId>,
+    pub client: Option<RuleId>,
+    pub device: Option<RuleId>,
+}
@@ -0,0 +1,26 @@
+/// Regex entry counts across all of a detector's parsers.
+///
+/// Only top-level entries are counted (bots, OSes, clients, engines, vendor
+/// fragments and device brand gates); per-brand model regexes are not.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DetectorStats {
+    /// Entries matched through the `regex` crate (via regex-filtered).
+    pub standard_entries: usize,
+    /// Entries that need `fancy_regex` (lookaround, backreferences, ...).
+    pub fancy_entries: usize,
+    /// Fancy-only entries discarded by
+    /// [`standard_regex_only`](crate::DeviceDetectorBuilder::standard_regex_only).
+    pub dropped_entries: usize,
+    /// Parsers whose regex-filtered set failed to build, so all their
+    /// entries are matched with `fancy_regex` instead (slower, same results).
+    pub filtered_fallbacks: usize,
+}
+
+impl std::ops::AddAssign for DetectorStats {
+    fn add_assign(&mut self, other: Self) {
+        self.standard_entries += other.standard_entries;
+        self.fancy_entries += other.fancy_entries;
+        self.dropped_entries += other.dropped_entries;
+        self.filtered_fallbacks += other.filtered_fallbacks;
+    }
+}
@@ -0,0 +1,14 @@
+use std::time::Duration;
+
+/// Cost of one parse, returned by
+/// [`DeviceDetector::parse_timed`](crate::DeviceDetector::parse_timed)
+/// (`timing` feature).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimingSnapshot {
+    /// Wall-clock time spent in the parse.
+    pub elapsed: Duration,
+    /// `Cow::Owned` strings the parse allocated: template substitutions
+    /// and values taken from client hints.  Detections made only of
+    /// borrowed dataset and UA slices report zero.
+    pub owned_allocations: usize,
+}
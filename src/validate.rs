@@ -0,0 +1,116 @@
+use super::db;
+use super::parser::full_pattern;
+use super::types::DatasetIssue;
+use std::path::Path;
+
+/// Load every YAML file under a Matomo `regexes/` dir and try to compile each
+/// regex, without building any parser.
+///
+/// A regex counts as valid if either the `regex` crate or `fancy_regex`
+/// accepts it (the same split `CompiledParser` makes).  Device files are
+/// optional, as in a non-strict build; every other file must exist.
+pub(crate) fn validate_dir(dir: &Path) -> Vec<DatasetIssue> {
+    let mut issues = Vec::new();
+    let client_dir = dir.join("client");
+
+    check_flat::<db::BotEntry>(&dir.join("bots.yml"), |e| (&e.name, &e.regex), &mut issues);
+    check_flat::<db::OsEntry>(&dir.join("oss.yml"), |e| (&e.name, &e.regex), &mut issues);
+    for file in [
+        "browsers.yml",
+        "feed_readers.yml",
+        "mobile_apps.yml",
+        "libraries.yml",
+        "mediaplayers.yml",
+        "pim.yml",
+    ] {
+        check_flat::<db::BrowserEntry>(
+            &client_dir.join(file),
+            |e| (&e.name, &e.regex),
+            &mut issues,
+        );
+    }
+    check_flat::<db::EngineEntry>(
+        &client_dir.join("browser_engine.yml"),
+        |e| (&e.name, &e.regex),
+        &mut issues,
+    );
+
+    let path = dir.join("vendorfragments.yml");
+    if let Some(map) = load::<db::VendorFragmentMap>(&path, &mut issues) {
+        for (brand, patterns) in &map {
+            for pattern in patterns {
+                check_regex(&path, brand, &format!("{}[^a-z0-9]+", pattern), &mut issues);
+            }
+        }
+    }
+
+    for file in ["apps.yml", "browsers.yml"] {
+        load::<db::HintMap>(&client_dir.join("hints").join(file), &mut issues);
+    }
+
+    let mut device_files: Vec<_> = std::fs::read_dir(dir.join("device"))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yml"))
+        .collect();
+    device_files.sort();
+    for path in device_files {
+        let Some(brands) = load::<db::DeviceBrandMap>(&path, &mut issues) else {
+            continue;
+        };
+        for (brand, entry) in &brands {
+            if let Some(regex) = &entry.regex {
+                check_regex(&path, brand, regex, &mut issues);
+            }
+            for model in entry.models.iter().flatten() {
+                let name = format!("{} / {}", brand, model.model.as_deref().unwrap_or(""));
+                check_regex(&path, &name, &model.regex, &mut issues);
+            }
+        }
+    }
+
+    issues
+}
+
+fn load<T: serde::de::DeserializeOwned>(path: &Path, issues: &mut Vec<DatasetIssue>) -> Option<T> {
+    let result = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_yaml::from_str(&content).map_err(|e| e.to_string()));
+    match result {
+        Ok(value) => Some(value),
+        Err(message) => {
+            issues.push(DatasetIssue {
+                file: path.to_path_buf(),
+                entry: String::new(),
+                message,
+            });
+            None
+        }
+    }
+}
+
+fn check_flat<T: serde::de::DeserializeOwned>(
+    path: &Path,
+    fields: impl Fn(&T) -> (&String, &String),
+    issues: &mut Vec<DatasetIssue>,
+) {
+    for entry in load::<Vec<T>>(path, issues).unwrap_or_default() {
+        let (name, regex) = fields(&entry);
+        check_regex(path, name, regex, issues);
+    }
+}
+
+fn check_regex(path: &Path, entry: &str, pattern: &str, issues: &mut Vec<DatasetIssue>) {
+    let full = full_pattern(pattern);
+    if regex::Regex::new(&full).is_ok() {
+        return;
+    }
+    if let Err(e) = fancy_regex::Regex::new(&full) {
+        issues.push(DatasetIssue {
+            file: path.to_path_buf(),
+            entry: entry.to_string(),
+            message: format!("invalid regex {:?}: {}", pattern, e),
+        });
+    }
+}
@@ -0,0 +1,11 @@
+<?php
+
+namespace DeviceDetector;
+
+class DeviceDetector
+{
+    /**
+     * Current version number of DeviceDetector
+     */
+    public const VERSION = '6.4.1';
+}
@@ -0,0 +1,1997 @@
+//! Behavioural tests against the small hand-written dataset in
+//! `tests/data/regexes`.  Unlike `integration.rs`, these do not need a
+//! vendored Matomo checkout.
+
+use device_detector_rs::{
+    matches_with_boundary, Client, ClientHints, ClientType, Detection, DetectionDiff,
+    DetectionField, DetectionStage, Device, DeviceDetector, DeviceFile, DeviceFileSpec,
+    DevicePrefilterKind, DeviceSelection, DeviceType, Os, PipelineStage, ResolutionClass, RuleIds,
+};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const DATA_DIR: &str = "tests/data/regexes";
+
+static DETECTOR: OnceLock<DeviceDetector> = OnceLock::new();
+
+fn detector() -> &'static DeviceDetector {
+    DETECTOR.get_or_init(|| {
+        DeviceDetector::from_dir(Path::new(DATA_DIR)).expect("failed to build DeviceDetector")
+    })
+}
+
+/// Copy the test dataset into a fresh temp dir, leaving out `skip` (paths
+/// relative to the dataset root), so tests can simulate incomplete checkouts.
+fn dataset_without(name: &str, skip: &[&str]) -> PathBuf {
+    fn copy(src: &Path, dst: &Path, root: &Path, skip: &[&str]) {
+        std::fs::create_dir_all(dst).unwrap();
+        for entry in std::fs::read_dir(src).unwrap() {
+            let path = entry.unwrap().path();
+            let rel = path.strip_prefix(root).unwrap();
+            if skip.iter().any(|s| Path::new(s) == rel) {
+                continue;
+            }
+            let target = dst.join(path.file_name().unwrap());
+            if path.is_dir() {
+                copy(&path, &target, root, skip);
+            } else {
+                std::fs::copy(&path, &target).unwrap();
+            }
+        }
+    }
+
+    let dst = std::env::temp_dir().join(format!("dd-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dst);
+    copy(Path::new(DATA_DIR), &dst, Path::new(DATA_DIR), skip);
+    dst
+}
+
+// ---------------------------------------------------------------------------
+// Loading
+// ---------------------------------------------------------------------------
+
+#[test]
+fn missing_device_file_is_skipped() {
+    let dir = dataset_without("missing-notebooks", &["device/notebooks.yml"]);
+    let dd = DeviceDetector::from_dir(&dir).expect("lenient build should succeed");
+    assert_eq!(dd.missing_device_files(), &["notebooks.yml"]);
+
+    let result = dd.parse("Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36");
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Google");
+    assert_eq!(device.model, "Pixel 8");
+
+    assert!(DeviceDetector::builder(&dir).strict(true).build().is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn oversized_pattern_falls_back_to_fancy_regex() {
+    // A pattern the regex crate accepts but regex-filtered's prefilter
+    // analysis rejects; the bot parser must still build and match.
+    let huge = format!("HugeBot-{}", "x".repeat(120_000));
+    let dir = dataset_without("oversized-pattern", &[]);
+    let bots = dir.join("bots.yml");
+    let mut yaml = std::fs::read_to_string(&bots).unwrap();
+    yaml.push_str(&format!("\n- regex: '{}'\n  name: 'Huge Bot'\n", huge));
+    std::fs::write(&bots, yaml).unwrap();
+
+    let dd = DeviceDetector::from_dir(&dir).expect("detector should still build");
+    assert_eq!(dd.stats().filtered_fallbacks, 1);
+    assert_eq!(detector().stats().filtered_fallbacks, 0);
+    assert_eq!(dd.parse(GOOGLEBOT_CHROME).bot().unwrap().name, "Googlebot");
+    assert_eq!(dd.parse(&huge).bot().unwrap().name, "Huge Bot");
+    assert!(!dd.parse(WINDOWS_CHROME).is_bot());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn every_device_file_exists() {
+    let device_dir = Path::new(DATA_DIR).join("device");
+    for file in DeviceFile::ALL {
+        let path = device_dir.join(file.file_name());
+        assert!(path.is_file(), "{} missing", path.display());
+    }
+
+    let names: Vec<&str> = DeviceFileSpec::matomo_defaults()
+        .iter()
+        .map(|spec| spec.file)
+        .collect();
+    assert_eq!(names, DeviceFile::ALL.map(DeviceFile::file_name));
+}
+
+#[test]
+fn validate_dir_reports_broken_regex() {
+    assert!(DeviceDetector::validate_dir(DATA_DIR).is_empty());
+
+    let dir = dataset_without("broken-regex", &[]);
+    let oss = dir.join("oss.yml");
+    let mut content = std::fs::read_to_string(&oss).unwrap();
+    content.push_str("\n- regex: 'Broken(OS'\n  name: 'Broken OS'\n  version: ''\n");
+    std::fs::write(&oss, content).unwrap();
+
+    let issues = DeviceDetector::validate_dir(&dir);
+    assert_eq!(issues.len(), 1, "{:?}", issues);
+    assert_eq!(issues[0].file, oss);
+    assert_eq!(issues[0].entry, "Broken OS");
+    assert!(
+        issues[0].message.contains("Broken(OS"),
+        "{}",
+        issues[0].message
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Needs the dataset the build script embedded, e.g.
+/// `DEVICE_DETECTOR_REGEXES=$PWD/tests/data/regexes cargo test --features precompiled`.
+#[cfg(feature = "precompiled")]
+#[test]
+fn precompiled_detects_bot_and_browser() {
+    let dd = DeviceDetector::precompiled().expect("precompiled build should succeed");
+
+    let result = dd.parse(GOOGLEBOT_CHROME);
+    assert_eq!(result.bot().expect("expected bot").name, "Googlebot");
+
+    let result = dd.parse(WINDOWS_CHROME);
+    assert_eq!(result.client().expect("expected client").name, "Chrome");
+    assert_eq!(result.os().expect("expected os").name, "Windows");
+}
+
+#[cfg(feature = "include_dir")]
+#[test]
+fn include_dir_matches_from_dir() {
+    static REGEXES: include_dir::Dir =
+        include_dir::include_dir!("$CARGO_MANIFEST_DIR/tests/data/regexes");
+    let dd = DeviceDetector::from_include_dir(&REGEXES).expect("embedded build should succeed");
+
+    let result = dd.parse(GOOGLEBOT_CHROME);
+    assert_eq!(result.bot().expect("expected bot").name, "Googlebot");
+
+    for ua in [WINDOWS_CHROME, MAC_SAFARI] {
+        assert_eq!(dd.parse(ua).into_owned(), detector().parse(ua).into_owned());
+    }
+    assert!(dd.missing_device_files().is_empty());
+}
+
+#[test]
+fn boundary_prefix_semantics() {
+    // The prefix only lets a pattern start at a non-alphanumeric boundary.
+    assert!(matches_with_boundary("Mozilla/5.0 (Linux; Android 14; Pixel 8)", "Pixel").unwrap());
+    assert!(matches_with_boundary("Mozilla/5.0 (Linux; Android 14; pixel 8)", "Pixel").unwrap());
+    assert!(
+        !matches_with_boundary("Mozilla/5.0 (Linux; Android 14; SuperPixel 8)", "Pixel").unwrap()
+    );
+    assert!(
+        !matches_with_boundary("Mozilla/5.0 (Linux; Android 14; Super-Pixel 8)", "Pixel").unwrap()
+    );
+    assert!(matches_with_boundary("Pixel 8", "Pixel").unwrap());
+    assert!(matches_with_boundary("ua", "(unclosed").is_err());
+}
+
+#[test]
+fn literal_min_len_keeps_detection() {
+    let ua = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .literal_min_len(2)
+        .build()
+        .unwrap();
+    let result = dd.parse(ua);
+    assert_eq!(
+        result.client().expect("expected client").name,
+        "Chrome Mobile"
+    );
+    assert_eq!(result.device().expect("expected device").model, "Pixel 8");
+}
+
+// ---------------------------------------------------------------------------
+// Client hints
+// ---------------------------------------------------------------------------
+
+#[test]
+fn model_hint_infers_brand() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let hints = ClientHints {
+        model: Some("Pixel 7".to_string()),
+        mobile: Some(true),
+        ..Default::default()
+    };
+
+    let result = dd.parse_with_hints(ua, Some(&hints));
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Google");
+    assert_eq!(device.model, "Pixel 7");
+}
+
+const MAC_SAFARI: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Safari/605.1.15";
+
+#[test]
+fn owned_hints_by_value() {
+    let ua = "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let result = detector().parse_owned_hints(
+        ua,
+        ClientHints {
+            model: Some("Pixel 7".to_string()),
+            mobile: Some(true),
+            ..Default::default()
+        },
+    );
+    let device = result.device.expect("expected device");
+    assert_eq!(device.brand, "Google");
+    assert_eq!(device.model, "Pixel 7");
+}
+
+#[test]
+fn arch_hint_detects_apple_silicon_mac() {
+    let dd = detector();
+    let hints = ClientHints {
+        architecture: Some("\"arm\"".to_string()),
+        ..Default::default()
+    };
+
+    let result = dd.parse_with_hints(MAC_SAFARI, Some(&hints));
+    assert_eq!(result.os().expect("expected os").platform, "ARM");
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Apple");
+    assert_eq!(device.model, "Mac");
+
+    let intel = dd.parse(MAC_SAFARI);
+    assert_eq!(intel.os().unwrap().platform, "");
+    assert_eq!(intel.device().unwrap().model, "");
+}
+
+#[test]
+fn model_hint_does_not_override_ua_brand() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Linux; Android 13; SM-G991B) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let hints = ClientHints {
+        model: Some("Pixel 7".to_string()),
+        ..Default::default()
+    };
+
+    let result = dd.parse_with_hints(ua, Some(&hints));
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Samsung");
+}
+
+#[test]
+fn model_hint_ignored_on_desktop() {
+    let dd = detector();
+    let hints = ClientHints {
+        model: Some("Pixel 7".to_string()),
+        ..Default::default()
+    };
+
+    let result = dd.parse_with_hints(WINDOWS_CHROME, Some(&hints));
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Desktop));
+    assert_eq!(device.model, "");
+
+    // A mobile hint vouches for the model even on a desktop UA.
+    let hints = ClientHints {
+        mobile: Some(true),
+        ..hints
+    };
+    let result = dd.parse_with_hints(WINDOWS_CHROME, Some(&hints));
+    assert_eq!(result.device().expect("expected device").model, "Pixel 7");
+}
+
+#[test]
+fn hint_debug_summary_and_influence() {
+    let hints = ClientHints {
+        mobile: Some(true),
+        platform: Some("Android".to_string()),
+        model: Some("Pixel 7".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        hints.debug_summary(),
+        "Sec-CH-UA-Model: Pixel 7\nSec-CH-UA-Mobile: ?1\nSec-CH-UA-Platform: Android"
+    );
+    assert_eq!(ClientHints::default().debug_summary(), "");
+
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let influence = dd.hint_influence(ua, &hints);
+    let headers: Vec<&str> = influence.iter().map(|(header, _)| *header).collect();
+    assert_eq!(headers, ["Sec-CH-UA-Model"]);
+    let (_, diff) = &influence[0];
+    let fields: Vec<&str> = diff.changes.iter().map(|c| c.field.as_str()).collect();
+    assert_eq!(fields, ["device.brand", "device.model"]);
+}
+
+#[test]
+fn legacy_full_version_hint_sets_browser_version() {
+    let dd = detector();
+    let hints = |version: &str| ClientHints {
+        full_version: Some(format!("\"{}\"", version)),
+        ..Default::default()
+    };
+
+    let result = dd.parse_with_hints(WINDOWS_CHROME, Some(&hints("120.0.6099.109")));
+    let client = result.client().expect("expected client");
+    assert_eq!(client.name, "Chrome");
+    assert_eq!(client.version, "120.0.6099.109");
+
+    // A hint for another major release does not describe this browser.
+    let result = dd.parse_with_hints(WINDOWS_CHROME, Some(&hints("119.0.6045.199")));
+    assert_eq!(result.client().unwrap().version, "120.0.0.0");
+}
+
+#[test]
+fn real_brands_drop_greasing() {
+    let grease = |brand: &str| (brand.to_string(), "99".to_string());
+    for greasing in [
+        "Not.A/Brand",
+        "Not;A=Brand",
+        " Not A;Brand",
+        "Not_A Brand",
+        "Not)A;Brand",
+    ] {
+        let hints = ClientHints {
+            brands: vec![
+                ("Chromium".to_string(), "120".to_string()),
+                grease(greasing),
+                ("Google Chrome".to_string(), "120".to_string()),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            hints.real_brands(),
+            [("Chromium", "120"), ("Google Chrome", "120")],
+            "{:?}",
+            greasing
+        );
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Operating systems
+// ---------------------------------------------------------------------------
+
+#[test]
+fn android_version_inferred_from_chrome() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Linux; Android; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let result = dd.parse(ua);
+    let os = result.os().expect("expected os");
+    assert_eq!(os.name, "Android");
+    assert_eq!(os.version, "8.0");
+    assert!(os.version_inferred);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Smartphone));
+
+    let ua = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let result = dd.parse(ua);
+    let os = result.os().expect("expected os");
+    assert_eq!(os.version, "14");
+    assert!(!os.version_inferred);
+}
+
+// Ubuntu needs fancy_regex, generic Linux doesn't; file order must still
+// decide across the two sets.
+#[test]
+fn fancy_os_entry_keeps_priority_over_filtered_set() {
+    let dd = detector();
+    for (ua, name, version) in [
+        (
+            "Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:120.0) Gecko/20100101 Firefox/120.0",
+            "Ubuntu",
+            "",
+        ),
+        (
+            "Mozilla/5.0 (X11; Linux x86_64; Ubuntu/22.04) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+            "Ubuntu",
+            "22.04",
+        ),
+        (
+            "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+            "GNU/Linux",
+            "",
+        ),
+    ] {
+        let result = dd.parse(ua);
+        let os = result.os().expect("expected os");
+        assert_eq!(os.name, name, "{}", ua);
+        assert_eq!(os.version, version, "{}", ua);
+    }
+}
+
+#[test]
+fn os_key_is_normalized() {
+    let dd = detector();
+    let rt_8 =
+        dd.parse("Mozilla/5.0 (compatible; MSIE 10.0; Windows NT 6.2; ARM; Trident/6.0; Touch)");
+    let rt_81 =
+        dd.parse("Mozilla/5.0 (Windows NT 6.3; ARM; Trident/7.0; Touch; rv:11.0) like Gecko");
+    let (rt_8, rt_81) = (
+        rt_8.os().expect("expected os"),
+        rt_81.os().expect("expected os"),
+    );
+    assert_eq!(rt_8.name, "Windows RT");
+    assert_eq!(rt_8.key(), "windows-rt");
+    assert_eq!(rt_8.key(), rt_81.key());
+
+    let linux = dd.parse_owned("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
+    let linux = linux.os.expect("expected os");
+    assert_eq!(linux.name, "GNU/Linux");
+    assert_eq!(linux.key(), "gnu-linux");
+}
+
+#[test]
+fn minority_mobile_os_is_smartphone() {
+    let dd = detector();
+    for (ua, name, version) in [
+        (
+            "Mozilla/5.0 (Linux; U; Sailfish 3.0; Mobile; rv:45.0) Gecko/45.0 Firefox/45.0 SailfishBrowser/1.0",
+            "Sailfish OS",
+            "3.0",
+        ),
+        (
+            "Mozilla/5.0 (Linux; Ubuntu 16.04 like Android 9) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/87.0.4280.144 Mobile Safari/537.36",
+            "Ubuntu Touch",
+            "16.04",
+        ),
+    ] {
+        let result = dd.parse(ua);
+        let os = result.os().expect("expected os");
+        assert_eq!(os.name, name, "{}", ua);
+        assert_eq!(os.version, version, "{}", ua);
+        let device = result.device().expect("expected device");
+        assert_eq!(device.kind, Some(DeviceType::Smartphone), "{}", ua);
+    }
+}
+
+#[test]
+fn os_at_least_compares_versions() {
+    let dd = detector();
+    let ios17 = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1_2 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1.2 Mobile/15E148 Safari/604.1";
+    let ios15 = "Mozilla/5.0 (iPhone; CPU iPhone OS 15_7 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/15.6.1 Mobile/15E148 Safari/604.1";
+
+    let result = dd.parse(ios17);
+    assert!(result.os_at_least("iOS", "16"));
+    assert!(result.os_at_least("ios", "17.1.2"));
+    assert!(!result.os_at_least("Android", "1"));
+    assert!(!dd.parse(ios15).os_at_least("iOS", "16"));
+    assert!(!dd.parse(GOOGLEBOT_CHROME).os_at_least("Android", "1"));
+}
+
+// ---------------------------------------------------------------------------
+// Clients
+// ---------------------------------------------------------------------------
+
+#[test]
+fn browser_extension_runtime() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Tampermonkey/5.1.1";
+
+    let result = dd.parse(ua);
+    let client = result.client().expect("expected client");
+    assert_eq!(client.kind, ClientType::BrowserExtension);
+    assert_eq!(client.name, "Tampermonkey");
+    assert_eq!(client.version, "5.1.1");
+}
+
+#[test]
+fn android_webview_flag() {
+    let dd = detector();
+
+    let webview = "Mozilla/5.0 (Linux; Android 10; K; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/120.0.0.0 Mobile Safari/537.36";
+    assert!(dd.parse(webview).is_android_webview());
+
+    let chrome = "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    assert!(!dd.parse(chrome).is_android_webview());
+}
+
+#[test]
+fn inferred_engine_family() {
+    let safari = Client {
+        kind: ClientType::Browser,
+        name: Cow::Borrowed("Safari"),
+        version: Cow::Borrowed("17.1"),
+        engine: Cow::Borrowed(""),
+        engine_version: Cow::Borrowed(""),
+        mode: None,
+        channel: None,
+    };
+    assert_eq!(safari.inferred_engine_family(), Some("WebKit"));
+
+    // The engine token wins when the entry has one.
+    let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:109.0) Gecko/20100101 Firefox/120.0";
+    let result = detector().parse(ua);
+    let client = result.client().expect("expected client");
+    assert_eq!(client.inferred_engine_family(), Some("Gecko"));
+
+    // A browser named by X-Requested-With carries no engine of its own.
+    let ua = "Mozilla/5.0 (Linux; Android 14; Pixel 8; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/120.0.0.0 Mobile Safari/537.36";
+    let hints = ClientHints {
+        x_requested_with: Some("com.brave.browser".to_string()),
+        ..Default::default()
+    };
+    let owned = detector().parse_owned_with_hints(ua, Some(&hints));
+    let client = owned.client.expect("expected client");
+    assert_eq!(client.name, "Brave");
+    assert_eq!(client.engine, "");
+    assert_eq!(client.inferred_engine_family(), Some("Blink"));
+
+    let app = Client {
+        kind: ClientType::MobileApp,
+        name: Cow::Borrowed("Facebook"),
+        ..safari
+    };
+    assert_eq!(app.inferred_engine_family(), None);
+}
+
+#[test]
+fn engine_version_comes_from_engine_token() {
+    let dd = detector();
+
+    let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:109.0) Gecko/20100101 Firefox/120.0";
+    let result = dd.parse(ua);
+    let client = result.client().expect("expected client");
+    assert_eq!(client.name, "Firefox");
+    assert_eq!(client.version, "120.0");
+    assert_eq!(client.engine, "Gecko");
+    assert_eq!(client.engine_version, "109.0");
+
+    let result = dd.parse(MAC_SAFARI);
+    let client = result.client().expect("expected client");
+    assert_eq!(client.engine, "WebKit");
+    assert_eq!(client.engine_version, "605.1.15");
+
+    let result = dd.parse(WINDOWS_CHROME);
+    let client = result.client().expect("expected client");
+    assert_eq!(client.engine, "Blink");
+    assert_eq!(client.engine_version, client.version);
+}
+
+#[test]
+fn all_engines_lists_every_claimed_engine() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+    let engines = dd.all_engines(ua);
+    let names: Vec<&str> = engines.iter().map(|(name, _)| name.as_str()).collect();
+    assert!(names.contains(&"Blink"), "{:?}", engines);
+    assert!(names.contains(&"WebKit"), "{:?}", engines);
+    assert!(engines.contains(&("Blink".to_string(), "120.0.0.0".to_string())));
+
+    let result = dd.parse(ua);
+    assert_eq!(result.client().expect("expected client").engine, "Blink");
+}
+
+#[test]
+fn client_mode_tokens() {
+    let dd = detector();
+    let base = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    assert_eq!(dd.parse(base).client().expect("expected client").mode, None);
+
+    let reader = format!("{} ReaderMode", base);
+    let result = dd.parse(&reader);
+    let client = result.client().expect("expected client");
+    assert_eq!(client.name, "Chrome Mobile");
+    assert_eq!(client.mode, Some("reader"));
+
+    let secret = format!("{} SecretMode", base);
+    assert_eq!(
+        dd.parse(&secret).client().expect("expected client").mode,
+        Some("private")
+    );
+
+    // IE 11 in compatibility view claims MSIE 7.0 on its own Trident engine.
+    let compat = "Mozilla/4.0 (compatible; MSIE 7.0; Windows NT 10.0; WOW64; Trident/7.0; .NET4.0C; .NET4.0E)";
+    let result = dd.parse(compat);
+    let client = result.client().expect("expected client");
+    assert_eq!(client.name, "Internet Explorer");
+    assert_eq!(client.mode, Some("compatibility"));
+    assert_eq!(
+        dd.parse_owned(compat).client.unwrap().mode,
+        Some("compatibility")
+    );
+
+    let ie10 = "Mozilla/5.0 (compatible; MSIE 10.0; Windows NT 6.1; Trident/6.0)";
+    assert_eq!(dd.parse(ie10).client().expect("expected client").mode, None);
+}
+
+#[test]
+fn browser_channel_tokens() {
+    let dd = detector();
+    let channel = |ua: &str| dd.parse(ua).client().expect("expected client").channel;
+
+    assert_eq!(channel(WINDOWS_CHROME), None);
+    let nightly = "Mozilla/5.0 (X11; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0a1";
+    assert_eq!(channel(nightly), Some("nightly"));
+    let beta = "Mozilla/5.0 (X11; Linux x86_64; rv:124.0) Gecko/20100101 Firefox/124.0b9";
+    assert_eq!(channel(beta), Some("beta"));
+    let canary = format!("{} Canary", WINDOWS_CHROME);
+    assert_eq!(channel(&canary), Some("canary"));
+
+    let client = dd.parse_owned(nightly).client.expect("expected client");
+    assert_eq!(client.channel, Some("nightly"));
+}
+
+#[test]
+fn same_class_ignores_versions() {
+    let dd = detector();
+    let older = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.6045.160 Safari/537.36";
+    let firefox =
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:109.0) Gecko/20100101 Firefox/120.0";
+
+    let chrome = dd.parse(WINDOWS_CHROME);
+    assert!(chrome.same_class_as(&dd.parse(older)));
+    assert!(!chrome.same_class_as(&dd.parse(firefox)));
+    assert!(!chrome.same_class_as(&dd.parse(MAC_SAFARI)));
+}
+
+#[test]
+fn fingerprint_is_stable() {
+    let dd = detector();
+    let chrome = dd.parse(WINDOWS_CHROME);
+    let owned = dd.parse_owned(WINDOWS_CHROME);
+    assert_eq!(chrome.fingerprint(), dd.parse(WINDOWS_CHROME).fingerprint());
+    assert_eq!(chrome.fingerprint(), owned.fingerprint());
+    // Pinned: a change here breaks every stored fingerprint.
+    assert_eq!(chrome.fingerprint(), 10444221997328595221);
+
+    // The engine version is not part of it, the browser version is.
+    let other_build = WINDOWS_CHROME.replace("AppleWebKit/537.36", "AppleWebKit/537.99");
+    assert_eq!(chrome.fingerprint(), dd.parse(&other_build).fingerprint());
+    let older = WINDOWS_CHROME.replace("Chrome/120.0.0.0", "Chrome/119.0.0.0");
+    assert_ne!(chrome.fingerprint(), dd.parse(&older).fingerprint());
+    assert_ne!(chrome.fingerprint(), dd.parse(MAC_SAFARI).fingerprint());
+}
+
+#[test]
+fn detect_client_type_agrees_with_parse() {
+    let dd = detector();
+    for ua in [
+        WINDOWS_CHROME,
+        MAC_SAFARI,
+        "Mozilla/5.0 (Windows NT 10.0; WOW64; Trident/7.0; rv:11.0) like Gecko",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Tampermonkey/5.1",
+        "curl/8.4.0",
+    ] {
+        let expected = dd.parse(ua).client().map(|c| c.kind);
+        assert_eq!(dd.detect_client_type(ua), expected, "{}", ua);
+    }
+}
+
+#[test]
+fn version_without_template_is_first_numeric_group() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Lunascape/WebKit 6.15.2 Chrome/120.0.0.0 Safari/537.36";
+    let result = dd.parse(ua);
+    let client = result.client().expect("expected client");
+    assert_eq!(client.name, "Lunascape");
+    assert_eq!(client.version, "6.15.2");
+}
+
+#[test]
+fn http_libraries_are_library_clients() {
+    let dd = detector();
+    for (ua, name, version) in [
+        ("okhttp/4.12.0", "OkHttp", "4.12.0"),
+        ("axios/1.6.2", "Axios", "1.6.2"),
+        ("Python-urllib/3.11", "Python urllib", "3.11"),
+        ("reqwest/0.11.24", "reqwest", "0.11.24"),
+        ("ureq/2.9.1", "ureq", "2.9.1"),
+    ] {
+        let result = dd.parse(ua);
+        let client = result.client().expect("expected client");
+        assert_eq!(client.kind, ClientType::Library, "{}", ua);
+        assert_eq!(client.name, name, "{}", ua);
+        assert_eq!(client.version, version, "{}", ua);
+    }
+
+    // libraries.yml is consulted before the built-in supplement.
+    let rule = |ua| dd.parse(ua).rule_ids().client.unwrap().file;
+    assert_eq!(rule("okhttp/4.12.0"), "libraries.yml");
+    assert_eq!(rule("reqwest/0.11.24"), "<builtin:http_libraries>");
+}
+
+// ---------------------------------------------------------------------------
+// Devices
+// ---------------------------------------------------------------------------
+
+#[test]
+fn prefer_notebook_type() {
+    let fbmd = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 [FBAN/EMA;FBLC/en_US;FBMD/Inspiron]";
+    let laptop = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; ThinkPad) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+    let kind = |dd: &DeviceDetector, ua| dd.parse(ua).device().and_then(|d| d.kind);
+
+    // Matomo types both as desktops.
+    assert_eq!(kind(detector(), fbmd), Some(DeviceType::Desktop));
+    assert_eq!(kind(detector(), laptop), Some(DeviceType::Desktop));
+
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .prefer_notebook_type(true)
+        .build()
+        .unwrap();
+    let result = dd.parse(fbmd);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Notebook));
+    assert_eq!(device.brand, "Dell");
+    assert_eq!(kind(&dd, laptop), Some(DeviceType::Notebook));
+    assert_eq!(kind(&dd, WINDOWS_CHROME), Some(DeviceType::Desktop));
+}
+
+#[test]
+fn chrome_os_tablet_vs_laptop() {
+    let dd = detector();
+
+    let laptop = "Mozilla/5.0 (X11; CrOS x86_64 15359.58.0) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+    let result = dd.parse(laptop);
+    assert_eq!(result.os().expect("expected os").name, "Chrome OS");
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Desktop));
+
+    let tablet = "Mozilla/5.0 (X11; CrOS aarch64 15359.58.0; Touch) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+    let result = dd.parse(tablet);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Tablet));
+
+    // Tablet mode on a convertible shows up only in the form-factor hint.
+    let hints = ClientHints {
+        form_factors: vec!["\"Tablet\"".to_string()],
+        ..Default::default()
+    };
+    let result = dd.parse_with_hints(laptop, Some(&hints));
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Tablet));
+}
+
+#[test]
+fn spoofed_mac_token_does_not_make_apple() {
+    let dd = detector();
+
+    // The Mac token wins the OS match (the dataset has no NT 6.1 entry),
+    // but the Windows token next to it rules out an Apple device.
+    let ua = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7; Windows NT 6.1) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+    let result = dd.parse(ua);
+    assert_eq!(result.os().expect("expected os").name, "Mac");
+    assert!(result.device().is_none_or(|d| d.brand != "Apple"));
+    assert_eq!(dd.detect_brand(ua), None);
+
+    // A device parser's Apple match on a Windows UA is cleared as before.
+    let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; iPhone) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+    let result = dd.parse(ua);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "");
+    assert_eq!(device.kind, Some(DeviceType::Desktop));
+
+    let result = dd.parse(MAC_SAFARI);
+    assert_eq!(result.device().expect("expected device").brand, "Apple");
+    assert_eq!(dd.detect_brand(MAC_SAFARI), Some("Apple"));
+}
+
+#[test]
+fn odm_model_brand_override_and_empty_brand() {
+    let dd = detector();
+
+    let rebranded = "Mozilla/5.0 (Linux; Android 5.1; RK3288-PIPO X9) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/90.0.4430.91 Safari/537.36";
+    let result = dd.parse(rebranded);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Pipo");
+    assert_eq!(device.model, "X9");
+
+    // `brand: ''` on the model means "keep the generic brand", not "no brand".
+    let generic = "Mozilla/5.0 (Linux; Android 7.1; RK3328 Build/NHG47K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/90.0.4430.91 Safari/537.36";
+    let result = dd.parse(generic);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Rockchip");
+    assert_eq!(device.model, "RK3328");
+    assert_eq!(device.kind, Some(DeviceType::Tablet));
+}
+
+#[test]
+fn tv_brand_from_model_under_generic_gate() {
+    let dd = detector();
+
+    let hisense = "Mozilla/5.0 (Linux; Android 9.0; HbbTV/1.5.1 (+DRM; Hisense; 55A7100F; V0000.01.00G.N0421; ;) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/84.0.4147.125 Safari/537.36";
+    let result = dd.parse(hisense);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Tv));
+    assert_eq!(device.brand, "Hisense");
+    assert_eq!(device.model, "55A7100F");
+    assert_eq!(dd.detect_brand(hisense), Some("Hisense"));
+
+    // The gate's own brand is "Unknown", which is reported as no brand.
+    let other = hisense.replace("Hisense", "Acme");
+    let result = dd.parse(&other);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Tv));
+    assert_eq!(device.brand, "");
+    assert_eq!(device.model, "");
+}
+
+#[test]
+fn device_file_default_type_override() {
+    let dir = dataset_without("custom-device-file", &[]);
+    // No `device:` on the brand, so the file's default type decides.
+    std::fs::write(
+        dir.join("device/robots.yml"),
+        "Boston Dynamics:\n  regex: 'SpotBrowser'\n  model: 'Spot'\n",
+    )
+    .unwrap();
+    let ua = "Mozilla/5.0 (X11; Linux aarch64) SpotBrowser/1.0";
+
+    let dd = DeviceDetector::builder(&dir)
+        .device_file(DeviceFileSpec::new("robots.yml", DeviceType::Peripheral))
+        .build()
+        .unwrap();
+    let result = dd.parse(ua);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Boston Dynamics");
+    assert_eq!(device.kind, Some(DeviceType::Peripheral));
+
+    let dd = DeviceDetector::builder(&dir)
+        .device_file(DeviceFileSpec::new("robots.yml", DeviceType::Peripheral))
+        .device_file(DeviceFileSpec::new("robots.yml", DeviceType::Wearable))
+        .build()
+        .unwrap();
+    let result = dd.parse(ua);
+    assert_eq!(
+        result.device().expect("expected device").kind,
+        Some(DeviceType::Wearable)
+    );
+
+    // Not registered: the file is ignored.
+    let dd = DeviceDetector::from_dir(&dir).unwrap();
+    let result = dd.parse(ua);
+    assert_ne!(
+        result.device().map(|d| d.brand.as_ref()),
+        Some("Boston Dynamics")
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn prefilter_implies_type_for_unlisted_brand() {
+    let camera = DeviceFileSpec {
+        prefilter: DevicePrefilterKind::Specific(r"(?i)Camera/\d".to_string()),
+        ..DeviceFileSpec::new("cameras.yml", DeviceType::Camera)
+    };
+    // No camera brand in cameras.yml matches "Acme".
+    let ua = "Mozilla/5.0 (Linux; Android 9; Acme C1) Camera/2.0";
+    let kind = |dd: &DeviceDetector| dd.parse(ua).device().and_then(|d| d.kind);
+
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .device_file(camera.clone())
+        .build()
+        .unwrap();
+    assert_ne!(kind(&dd), Some(DeviceType::Camera));
+
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .device_file(camera)
+        .prefilter_implies_type(true)
+        .build()
+        .unwrap();
+    assert_eq!(kind(&dd), Some(DeviceType::Camera));
+    assert_eq!(dd.parse(ua).device().unwrap().brand, "");
+    // A brand match in a later file still wins over the weak type.
+    let pixel = "Mozilla/5.0 (Linux; Android 14; Pixel 8) Camera/2.0";
+    assert_eq!(dd.parse(pixel).device().unwrap().brand, "Google");
+}
+
+#[test]
+fn brand_level_device_type_beats_file_default() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Linux; Android 4.4.3; KFTHWI Build/KTU84M) AppleWebKit/537.36 (KHTML, like Gecko) Silk/47.1.79 like Chrome/47.0.2526.80 Safari/537.36";
+
+    let result = dd.parse(ua);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Amazon");
+    assert_eq!(device.model, "Kindle Fire HDX 7");
+    assert_eq!(device.kind, Some(DeviceType::Tablet));
+}
+
+#[test]
+fn firefox_android_mobile_and_tablet_tokens() {
+    let dd = detector();
+    for (ua, kind) in [
+        (
+            "Mozilla/5.0 (Linux; Android 12; rv:109.0) Gecko/113.0 Firefox/113.0 Mobile",
+            DeviceType::Smartphone,
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 12; rv:109.0) Gecko/113.0 Firefox/113.0 Tablet",
+            DeviceType::Tablet,
+        ),
+        (
+            "Mozilla/5.0 (Android 14; Mobile; rv:121.0) Gecko/121.0 Firefox/121.0",
+            DeviceType::Smartphone,
+        ),
+        (
+            "Mozilla/5.0 (Android 14; Tablet; rv:121.0) Gecko/121.0 Firefox/121.0",
+            DeviceType::Tablet,
+        ),
+    ] {
+        let result = dd.parse(ua);
+        assert_eq!(result.client().expect("expected client").name, "Firefox");
+        let device = result.device().expect("expected device");
+        assert_eq!(device.kind, Some(kind), "{}", ua);
+    }
+}
+
+#[test]
+fn detect_brand_matches_full_parse() {
+    let dd = detector();
+    for ua in [
+        "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+        "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1",
+        "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Safari/605.1.15",
+        "Mozilla/5.0 (PlayStation; PlayStation 5/2.26) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/13.0 Safari/605.1.15",
+        "Mozilla/5.0 (X11; U; Linux armv7l like Android; en-us) AppleWebKit/531.2+ (KHTML, like Gecko) Version/5.0 Safari/533.2+ Kindle/3.0+",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+    ] {
+        let result = dd.parse(ua);
+        let expected = result.device().map(|d| d.brand.as_ref()).filter(|b| !b.is_empty());
+        assert_eq!(dd.detect_brand(ua), expected, "{}", ua);
+    }
+}
+
+#[test]
+fn foldables() {
+    let dd = detector();
+    for (ua, brand, model) in [
+        (
+            "Mozilla/5.0 (Linux; Android 9; SM-F900U) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/80.0.3987.99 Mobile Safari/537.36",
+            "Samsung",
+            "Galaxy Fold",
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 13; SM-F721B) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+            "Samsung",
+            "Galaxy Z Flip 4",
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 11; Surface Duo) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+            "Microsoft",
+            "Surface Duo",
+        ),
+    ] {
+        let result = dd.parse(ua);
+        let device = result.device().expect("expected device");
+        assert_eq!(device.kind, Some(DeviceType::Smartphone), "{}", ua);
+        assert_eq!(device.brand, brand);
+        assert_eq!(device.model, model);
+        assert!(device.is_foldable(), "{}", ua);
+    }
+
+    let result = dd.parse("Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36");
+    assert!(!result.device().expect("expected device").is_foldable());
+}
+
+#[test]
+fn clientless_desktop_os_is_desktop() {
+    let result = detector().parse("Mozilla/5.0 (Windows NT 10.0)");
+    assert_eq!(result.os().expect("expected os").name, "Windows");
+    assert!(result.client().is_none());
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Desktop));
+    assert_eq!(device.brand, "");
+
+    let result = detector().parse("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7)");
+    assert!(result.client().is_none());
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Desktop));
+    assert_eq!(device.brand, "Apple");
+}
+
+#[test]
+fn set_top_boxes_are_tvs() {
+    let dd = detector();
+    for (ua, brand, model) in [
+        (
+            "Mozilla/5.0 (Linux; Android 9; MIBOX4 Build/PI; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/120.0.6099.230 Safari/537.36",
+            "Xiaomi",
+            "Mi Box 4",
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 9; X96 Max Build/PPR1.180610.011) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+            "",
+            "",
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 7.1.2; AOSP on p212 Build/NHG47L) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/98.0.4758.101 Safari/537.36",
+            "",
+            "",
+        ),
+    ] {
+        let result = dd.parse(ua);
+        let device = result.device().expect("expected device");
+        assert_eq!(device.kind, Some(DeviceType::Tv), "{}", ua);
+        assert_eq!(device.brand, brand, "{}", ua);
+        assert_eq!(device.model, model);
+    }
+}
+
+#[test]
+fn tizen_tv_vs_phone() {
+    let dd = detector();
+
+    let tv = "Mozilla/5.0 (SMART-TV; LINUX; Tizen 6.0) AppleWebKit/537.36 (KHTML, like Gecko) 76.0.3809.146/6.0 TV Safari/537.36";
+    let result = dd.parse(tv);
+    let os = result.os().expect("expected os");
+    assert_eq!((os.name.as_ref(), os.version.as_ref()), ("Tizen", "6.0"));
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Tv));
+    assert_eq!(device.brand, "Samsung");
+
+    let phone = "Mozilla/5.0 (Linux; Tizen 2.3) AppleWebKit/538.1 (KHTML, like Gecko)Version/2.3 Mobile Safari/538.1";
+    let result = dd.parse(phone);
+    let os = result.os().expect("expected os");
+    assert_eq!((os.name.as_ref(), os.version.as_ref()), ("Tizen", "2.3"));
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Smartphone));
+    assert_eq!(device.brand, "Samsung");
+}
+
+// The console type comes from the device data, so desktop-OS inference
+// must leave it alone for Xbox, which runs Windows and says so.
+#[test]
+fn console_on_desktop_os_stays_console() {
+    let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; Xbox; Xbox Series X) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/48.0.2564.82 Safari/537.36 Edge/20.02";
+    let result = detector().parse(ua);
+    assert_eq!(result.os().expect("expected os").name, "Windows");
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Console));
+    assert_eq!(device.brand, "Microsoft");
+}
+
+#[test]
+fn most_specific_device_selection() {
+    // consoles.yml runs before mobiles.yml and only needs "PlayStation 5".
+    let ua = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36 PlayStation 5 Remote Play";
+
+    let result = detector().parse(ua);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Sony");
+    assert_eq!(device.model, "PlayStation 5");
+
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .device_selection(DeviceSelection::MostSpecific)
+        .build()
+        .unwrap();
+    let result = dd.parse(ua);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Smartphone));
+    assert_eq!(device.brand, "Google");
+    assert_eq!(device.model, "Pixel 8");
+}
+
+// ---------------------------------------------------------------------------
+// Bots
+// ---------------------------------------------------------------------------
+
+const GOOGLEBOT_CHROME: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+
+#[test]
+fn bot_short_circuits_by_default() {
+    let result = detector().parse(GOOGLEBOT_CHROME);
+    assert_eq!(result.bot().expect("expected bot").name, "Googlebot");
+    assert!(result.os().is_none());
+    assert!(result.client().is_none());
+}
+
+#[test]
+fn bot_without_short_circuit_runs_full_detection() {
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .bots_dont_short_circuit(true)
+        .build()
+        .unwrap();
+
+    let result = dd.parse(GOOGLEBOT_CHROME);
+    assert_eq!(result.bot().expect("expected bot").name, "Googlebot");
+    assert_eq!(result.os().expect("expected os").name, "Windows");
+    assert_eq!(result.client().expect("expected client").name, "Chrome");
+}
+
+#[test]
+fn detect_bot_producer_only() {
+    let dd = detector();
+    let producer = dd.detect_bot_producer(GOOGLEBOT_CHROME).expect("producer");
+    assert_eq!(producer.name, Some("Google Inc."));
+    assert_eq!(producer.url, Some("https://www.google.com"));
+
+    // Discordbot has no producer entry; a browser is no bot at all.
+    let discord = "Mozilla/5.0 (compatible; Discordbot/2.0)";
+    assert!(dd.detect_bot_producer(discord).is_none());
+    assert!(dd.detect_bot_producer(WINDOWS_CHROME).is_none());
+}
+
+#[test]
+fn link_preview_bots() {
+    let dd = detector();
+
+    let twitter = dd.parse("Twitterbot/1.0");
+    assert_eq!(twitter.bot().expect("expected bot").name, "Twitterbot");
+    assert!(twitter.is_link_preview_bot());
+    assert!(dd.parse_owned("Twitterbot/1.0").is_link_preview_bot());
+
+    // No category in the dataset; the curated name list catches it.
+    let discord = dd.parse("Mozilla/5.0 (compatible; Discordbot/2.0; +https://discordapp.com)");
+    assert_eq!(discord.bot().expect("expected bot").category, None);
+    assert!(discord.is_link_preview_bot());
+
+    assert!(!dd.parse(GOOGLEBOT_CHROME).is_link_preview_bot());
+    assert!(!dd.parse(WINDOWS_CHROME).is_link_preview_bot());
+}
+
+#[test]
+fn custom_tv_client_names() {
+    let ua = "Mozilla/5.0 (Linux; Android 11; X1) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 LoungeBrowser/2.4";
+    let result = detector().parse(ua);
+    assert_eq!(result.client().unwrap().name, "Lounge Browser");
+    assert_ne!(result.device().and_then(|d| d.kind), Some(DeviceType::Tv));
+
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .with_tv_client_names(["Lounge Browser"])
+        .build()
+        .unwrap();
+    let result = dd.parse(ua);
+    assert_eq!(result.device().and_then(|d| d.kind), Some(DeviceType::Tv));
+}
+
+#[test]
+fn reset_inconsistent_brand() {
+    let ua = "Mozilla/5.0 (iPad; CPU OS 17_1 like Mac OS X; SM-G991B) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1";
+    let result = detector().parse(ua);
+    assert_eq!(result.os().expect("expected os").name, "iOS");
+    assert_eq!(result.device().expect("expected device").brand, "Samsung");
+
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .reset_inconsistent_brand(true)
+        .build()
+        .unwrap();
+    let result = dd.parse(ua);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Apple");
+    assert_eq!(device.model, "");
+    assert_eq!(dd.detect_brand(ua), Some("Apple"));
+
+    // An Apple brand on a non-Apple OS is cleared either way.
+    let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; iPhone) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+    let result = dd.parse(ua);
+    assert_eq!(result.device().expect("expected device").brand, "");
+    assert_eq!(dd.detect_brand(ua), None);
+}
+
+#[test]
+fn android_model_from_build_token() {
+    let dd = detector();
+    // The Samsung gate matches, but no model regex covers the A series.
+    let ua = "Mozilla/5.0 (Linux; Android 11; SM-A525F Build/RP1A.200720.012; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/120.0.0.0 Mobile Safari/537.36";
+    let result = dd.parse(ua);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Samsung");
+    assert_eq!(device.model, "SM-A525F");
+}
+
+#[test]
+fn sonos_and_homepod_are_smart_speakers() {
+    let dd = detector();
+    for (ua, brand, model) in [
+        ("Linux UPnP/1.0 Sonos/63.2-88230 (ZPS12)", "Sonos", "One"),
+        (
+            "AppleCoreMedia/1.0.0.18L569 (HomePod; U; CPU OS 14_2 like Mac OS X; en_us)",
+            "Apple",
+            "HomePod",
+        ),
+        // No platform token the OS parser knows: the Apple brand still holds.
+        (
+            "server-bag [audioOS,16.0,20J5300d,AudioAccessory5,1]",
+            "Apple",
+            "HomePod",
+        ),
+    ] {
+        let result = dd.parse(ua);
+        let device = result.device().expect("expected device");
+        assert_eq!(device.kind, Some(DeviceType::SmartSpeaker), "{}", ua);
+        assert_eq!(device.brand, brand, "{}", ua);
+        assert_eq!(device.model, model, "{}", ua);
+    }
+}
+
+#[test]
+fn android_emulators_are_desktops() {
+    let dd = detector();
+    for ua in [
+        "Mozilla/5.0 (Linux; Android 9; Android-x86) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+        "Mozilla/5.0 (Linux; Android 9; SM-G973N Build/PPR1.190810.011; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/120.0.0.0 Mobile Safari/537.36 BlueStacks",
+    ] {
+        let result = dd.parse(ua);
+        assert!(result.is_emulator(), "{}", ua);
+        assert!(dd.parse_owned(ua).emulator, "{}", ua);
+        assert_eq!(result.os().unwrap().name, "Android", "{}", ua);
+        // The spoofed phone's brand and model go with its type.
+        let device = result.device().expect("expected device");
+        assert_eq!(device.kind, Some(DeviceType::Desktop), "{}", ua);
+        assert_eq!(device.brand, "", "{}", ua);
+        assert_eq!(device.model, "", "{}", ua);
+        assert!(result.rule_ids().device.is_none(), "{}", ua);
+        assert_eq!(dd.detect_brand(ua), None, "{}", ua);
+    }
+
+    // The same phone without the emulator marker stays a smartphone.
+    let phone = "Mozilla/5.0 (Linux; Android 9; SM-G973N Build/PPR1.190810.011; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/120.0.0.0 Mobile Safari/537.36";
+    let result = dd.parse(phone);
+    assert!(!result.is_emulator());
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Smartphone));
+    assert_eq!(device.brand, "Samsung");
+}
+
+#[test]
+fn echo_show_and_smart_tab_vs_tablet() {
+    let dd = detector();
+
+    let echo_show = "Mozilla/5.0 (Linux; Android 7.1.2; AEOKN Build/NS6297) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/70.0.3538.110 Safari/537.36";
+    let result = dd.parse(echo_show);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::SmartDisplay));
+    assert_eq!(device.brand, "Amazon");
+    assert_eq!(device.model, "Echo Show 5");
+
+    let tablet = "Mozilla/5.0 (Linux; Android 10; Lenovo TB-X606F) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+    let result = dd.parse(tablet);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Tablet));
+    assert_eq!(device.brand, "Lenovo");
+    assert_eq!(device.model, "Tab M10 FHD Plus");
+
+    // A Smart Tab is a tablet until it is docked in Show Mode.
+    let smart_tab = "Mozilla/5.0 (Linux; Android 9; Lenovo TB-X605FC) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+    let result = dd.parse(smart_tab);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Tablet));
+    assert_eq!(device.model, "Smart Tab M10");
+
+    let docked = format!("{} Alexa Show Mode", smart_tab);
+    let result = dd.parse(&docked);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::SmartDisplay));
+}
+
+#[test]
+fn phablet_model_survives_heuristics() {
+    let dd = detector();
+    let note = "Mozilla/5.0 (Linux; Android 10; SM-N950F) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let result = dd.parse(note);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Phablet));
+    assert_eq!(device.brand, "Samsung");
+    assert_eq!(device.model, "Galaxy Note 8");
+
+    // Neither the missing "Mobile" token (Chrome on Android → tablet) nor a
+    // non-mobile hint overrides the model's own type.
+    let no_mobile = note.replace(" Mobile Safari", " Safari");
+    let hints = ClientHints {
+        mobile: Some(false),
+        ..Default::default()
+    };
+    for result in [
+        dd.parse(&no_mobile),
+        dd.parse_with_hints(note, Some(&hints)),
+    ] {
+        let device = result.device().expect("expected device");
+        assert_eq!(device.kind, Some(DeviceType::Phablet));
+    }
+}
+
+#[test]
+fn feature_phone_data_on_smartphone_os() {
+    let dd = detector();
+    for (ua, kind) in [
+        (
+            "Mozilla/5.0 (Mobile; Nokia 8000 4G; rv:48.0) Gecko/48.0 Firefox/48.0 KAIOS/2.5.4",
+            DeviceType::FeaturePhone,
+        ),
+        // The same handset data on a smartphone OS is a smartphone.
+        (
+            "Mozilla/5.0 (Linux; Sailfish 4.4; Mobile; Nokia 8000 4G) AppleWebKit/537.36 (KHTML, like Gecko) SailfishBrowser/2.0 Mobile Safari/537.36",
+            DeviceType::Smartphone,
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 8.1; Nokia 8110 4G) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+            DeviceType::Smartphone,
+        ),
+    ] {
+        let result = dd.parse(ua);
+        let device = result.device().expect("expected device");
+        assert_eq!(device.brand, "Nokia", "{}", ua);
+        assert_eq!(device.kind, Some(kind), "{}", ua);
+    }
+}
+
+#[test]
+fn proxy_browser_on_feature_phone() {
+    let dd = detector();
+
+    // Nokia's brand-level type is smartphone; Java ME overrides it.
+    let uc_mini = "UCWEB/2.0 (Java; U; MIDP-2.0; en-US; Nokia206) U2/1.0.0 UCMini/10.9.8.1006 U2/1.0.0 Mobile";
+    let result = dd.parse(uc_mini);
+    assert_eq!(result.client().unwrap().name, "UC Browser Mini");
+    assert_eq!(result.os().unwrap().name, "Java ME");
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::FeaturePhone));
+    assert_eq!(device.brand, "Nokia");
+
+    let opera_mini = "Opera/9.80 (J2ME/MIDP; Opera Mini/4.2.14912/870; U; id) Presto/2.4.15";
+    let result = dd.parse(opera_mini);
+    assert_eq!(result.client().unwrap().name, "Opera Mini");
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::FeaturePhone));
+
+    // Opera Mini on a smartphone OS is left alone.
+    let android =
+        "Opera/9.80 (Android; Opera Mini/36.2.2254/119.132; U; id) Presto/2.12.423 Version/12.16";
+    let result = dd.parse(android);
+    assert_eq!(result.client().unwrap().name, "Opera Mini");
+    assert_ne!(
+        result.device().and_then(|d| d.kind),
+        Some(DeviceType::FeaturePhone)
+    );
+}
+
+#[test]
+fn resolution_class_from_known_models() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Web0S; Linux/SmartTV) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/79.0.3945.79 Safari/537.36 WebAppManager HbbTV/1.5.1 (+DRM; LGE; OLED55C1PUB; WEBOS6.0 03.20.40; W6_lm21a;)";
+    let result = dd.parse(ua);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Tv));
+    assert_eq!(device.brand, "LG");
+    assert_eq!(device.model, "OLED55C1");
+    assert_eq!(device.resolution_class(), Some(ResolutionClass::Uhd4k));
+    assert_eq!(
+        dd.parse_owned(ua).device.unwrap().resolution_class(),
+        Some(ResolutionClass::Uhd4k)
+    );
+
+    let note = "Mozilla/5.0 (Linux; Android 10; SM-N950F) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let result = dd.parse(note);
+    assert_eq!(
+        result.device().unwrap().resolution_class(),
+        Some(ResolutionClass::Qhd)
+    );
+
+    // Models missing from the table have no class.
+    let other = note.replace("SM-N950F", "SM-A525F");
+    assert_eq!(dd.parse(&other).device().unwrap().resolution_class(), None);
+}
+
+#[test]
+fn rank_bots_orders_by_entry_index() {
+    let ranked = detector().rank_bots("Mozilla/5.0 (compatible; Googlebot/2.1; GoogleOther)");
+    let names: Vec<&str> = ranked.iter().map(|(bot, _)| bot.name.as_ref()).collect();
+    assert_eq!(names, ["GoogleOther", "Googlebot"]);
+    assert!(ranked[0].1 < ranked[1].1);
+
+    assert!(detector()
+        .rank_bots("Mozilla/5.0 (Windows NT 10.0)")
+        .is_empty());
+}
+
+#[test]
+fn cast_smart_display_vs_speaker() {
+    let dd = detector();
+
+    let hub = "Mozilla/5.0 (X11; Linux aarch64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36 CrKey/1.56.500000 Google Nest Hub";
+    let result = dd.parse(hub);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::SmartDisplay));
+    assert_eq!(device.brand, "Google");
+
+    let audio = "Mozilla/5.0 (X11; Linux armv7l) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36 CrKey/1.56.500000 Google Nest Audio";
+    let result = dd.parse(audio);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::SmartSpeaker));
+    assert_eq!(device.brand, "Google");
+}
+
+// ---------------------------------------------------------------------------
+// Explain
+// ---------------------------------------------------------------------------
+
+#[cfg(feature = "timing")]
+#[test]
+fn timing_counts_owned_allocations() {
+    let dd = detector();
+
+    // A bot name straight from the dataset: nothing to substitute.
+    let (result, timing) = dd.parse_timed("Twitterbot/1.0", None);
+    assert!(result.is_bot());
+    assert_eq!(timing.owned_allocations, 0);
+
+    // OS version, client version and model all come from `$1` templates.
+    let ua = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let (result, timing) = dd.parse_timed(ua, None);
+    assert_eq!(result.device().expect("expected device").model, "Pixel 8");
+    assert!(timing.owned_allocations >= 3, "{timing:?}");
+}
+
+#[test]
+fn near_misses_point_at_unmatched_literals() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/nightly";
+
+    let misses = dd.near_misses(ua, "browsers.yml");
+    assert_eq!(misses.len(), 1);
+    assert!(misses[0].1.starts_with("Firefox/"));
+
+    assert!(dd.near_misses(WINDOWS_CHROME, "browsers.yml").is_empty());
+    assert!(dd.near_misses(ua, "no-such-file.yml").is_empty());
+}
+
+#[test]
+fn rule_ids_are_stable() {
+    let dd = detector();
+    let first = *dd.parse(WINDOWS_CHROME).rule_ids();
+    let second = *dd.parse(WINDOWS_CHROME).rule_ids();
+    assert_eq!(first, second);
+
+    let client = first.client.expect("expected client rule");
+    assert_eq!(client.file, "browsers.yml");
+    assert_eq!(client.model, None);
+    assert_eq!(first.os.expect("expected os rule").file, "oss.yml");
+    assert_eq!(first.bot, None);
+
+    // Firefox is a different entry of the same file.
+    let firefox =
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:109.0) Gecko/20100101 Firefox/120.0";
+    let other = dd.parse(firefox).rule_ids().client.unwrap();
+    assert_eq!(other.file, "browsers.yml");
+    assert_ne!(other.index, client.index);
+
+    let result = dd.parse("Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36");
+    let device = result.rule_ids().device.expect("expected device rule");
+    assert_eq!(device.file, "mobiles.yml");
+    assert_eq!(device.index, 0);
+    assert_eq!(device.model, Some(0));
+}
+
+#[test]
+fn explain_reports_source_patterns() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+
+    let explanation = dd.explain(ua);
+    assert!(explanation.bot.is_none());
+    assert!(explanation.os.unwrap().contains("Android"));
+    assert!(explanation.client.unwrap().contains("Chrome"));
+    assert_eq!(explanation.device_brand, Some("Nexus|Pixel"));
+    assert_eq!(explanation.device_model, Some(r"Pixel (\d+[a-z]?)"));
+}
+
+#[test]
+fn parse_with_meta_matches_explain() {
+    let dd = detector();
+    let pixel = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let pixel_7 = "Mozilla/5.0 (Linux; Android 13; Pixel 7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.0.0 Mobile Safari/537.36";
+
+    let (result, meta) = dd.parse_with_meta(pixel);
+    let rule_ids = result.rule_ids();
+    assert_eq!(meta.os, rule_ids.os.map(|r| r.index));
+    assert_eq!(meta.client, rule_ids.client.map(|r| r.index));
+    assert_eq!(meta.device, rule_ids.device.map(|r| r.index));
+    assert_eq!(meta.device_model, rule_ids.device.and_then(|r| r.model));
+    assert!(!meta.hints_used);
+
+    // A stage has an index exactly when explain names a pattern for it, and
+    // the same patterns give the same indices.
+    let explanation = dd.explain(pixel);
+    assert_eq!(meta.bot.is_some(), explanation.bot.is_some());
+    assert_eq!(meta.os.is_some(), explanation.os.is_some());
+    assert_eq!(meta.client.is_some(), explanation.client.is_some());
+    assert_eq!(meta.device.is_some(), explanation.device_brand.is_some());
+    assert_eq!(explanation.device_model, dd.explain(pixel_7).device_model);
+    assert_eq!(dd.parse_with_meta(pixel_7).1, meta);
+
+    let (_, firefox) = dd.parse_with_meta(
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:109.0) Gecko/20100101 Firefox/120.0",
+    );
+    assert_ne!(firefox.client, meta.client);
+
+    let hints = ClientHints::default();
+    assert!(dd.parse_with_hints_meta(pixel, Some(&hints)).1.hints_used);
+}
+
+#[test]
+fn parse_without_device_keeps_os_and_client() {
+    let dd = detector();
+    let android_wv = "Mozilla/5.0 (Linux; Android 13; SM-A525F Build/TP1A.220624.014; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/120.0.0.0 Mobile Safari/537.36";
+    let summary = |d: &Detection| {
+        let (bot, os, client) = (d.bot(), d.os(), d.client());
+        format!("{:?} {:?} {:?} {}", bot, os, client, d.android_webview)
+    };
+    for ua in [WINDOWS_CHROME, MAC_SAFARI, GOOGLEBOT_CHROME, android_wv] {
+        let light = dd.parse_without_device(ua, None);
+        assert!(light.device().is_none(), "{}", ua);
+        assert_eq!(summary(&light), summary(&dd.parse(ua)), "{}", ua);
+    }
+    assert!(dd.parse(android_wv).device().is_some());
+}
+
+#[test]
+fn dataset_version_from_checkout() {
+    assert_eq!(detector().dataset_version(), Some("6.4.1"));
+
+    // A bare copy of the regexes dir has no version marker...
+    let dir = dataset_without("no-version", &[]);
+    let dd = DeviceDetector::from_dir(&dir).unwrap();
+    assert_eq!(dd.dataset_version(), None);
+
+    // ...until a VERSION file is dropped in.
+    std::fs::write(dir.join("VERSION"), "6.5.0\n").unwrap();
+    let dd = DeviceDetector::from_dir(&dir).unwrap();
+    assert_eq!(dd.dataset_version(), Some("6.5.0"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn unknown_xrw_package_as_app() {
+    let ua = "Mozilla/5.0 (Linux; Android 14; Pixel 8; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/120.0.0.0 Mobile Safari/537.36";
+    let hints = ClientHints {
+        x_requested_with: Some("com.example.internal".to_string()),
+        ..Default::default()
+    };
+
+    let result = detector().parse_with_hints(ua, Some(&hints));
+    assert_eq!(result.client().unwrap().name, "Chrome Mobile");
+
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .unknown_xrw_as_app(true)
+        .build()
+        .unwrap();
+    let result = dd.parse_with_hints(ua, Some(&hints));
+    let client = result.client().expect("expected client");
+    assert_eq!(client.kind, ClientType::MobileApp);
+    assert_eq!(client.name, "com.example.internal");
+
+    // Listed packages still resolve through apps.yml, and AJAX requests are
+    // not apps.
+    let hints = ClientHints {
+        x_requested_with: Some("com.facebook.katana".to_string()),
+        ..Default::default()
+    };
+    let result = dd.parse_with_hints(ua, Some(&hints));
+    assert_eq!(result.client().unwrap().name, "Facebook");
+
+    let hints = ClientHints {
+        x_requested_with: Some("XMLHttpRequest".to_string()),
+        ..Default::default()
+    };
+    let result = dd.parse_with_hints(ua, Some(&hints));
+    assert_eq!(result.client().unwrap().name, "Chrome Mobile");
+}
+
+#[test]
+fn multi_token_xrw_resolves_first_known_package() {
+    let ua = "Mozilla/5.0 (Linux; Android 14; Pixel 8; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/120.0.0.0 Mobile Safari/537.36";
+    let client_for = |xrw: &str| {
+        let hints = ClientHints {
+            x_requested_with: Some(xrw.to_string()),
+            ..Default::default()
+        };
+        let result = detector().parse_with_hints(ua, Some(&hints));
+        let client = result.client().expect("expected client");
+        (client.kind, client.name.to_string())
+    };
+
+    let facebook = (ClientType::MobileApp, "Facebook".to_string());
+    assert_eq!(client_for("com.facebook.katana"), facebook);
+    assert_eq!(client_for("XMLHttpRequest, com.facebook.katana"), facebook);
+    assert_eq!(client_for("com.example.app com.facebook.katana"), facebook);
+    assert_eq!(
+        client_for("com.brave.browser,com.facebook.katana"),
+        (ClientType::Browser, "Brave".to_string())
+    );
+    assert_eq!(
+        client_for("XMLHttpRequest, com.example.proxy"),
+        (ClientType::Browser, "Chrome Mobile".to_string())
+    );
+}
+
+#[test]
+fn not_mobile_hint_overrides_ua_heuristics() {
+    let dd = detector();
+    // Chrome's "desktop site" mode on Android: X11 UA, Android platform hint.
+    let ua = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+    let mut hints = ClientHints {
+        platform: Some("Android".to_string()),
+        platform_version: Some("14.0.0".to_string()),
+        ..Default::default()
+    };
+
+    let result = dd.parse_with_hints(ua, Some(&hints));
+    assert_eq!(result.os().unwrap().name, "Android");
+    assert_eq!(result.device().unwrap().kind, Some(DeviceType::Tablet));
+
+    hints.mobile = Some(false);
+    let result = dd.parse_with_hints(ua, Some(&hints));
+    assert_eq!(result.os().unwrap().name, "Android");
+    assert!(result.device().and_then(|d| d.kind).is_none());
+
+    // A device-parser match is data-driven and keeps its type.
+    let ua = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+    let result = dd.parse_with_hints(ua, Some(&hints));
+    assert_eq!(result.device().unwrap().kind, Some(DeviceType::Smartphone));
+}
+
+#[test]
+fn desktop_fragment_yields_to_mobile_hint() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Desktop; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+    let result = dd.parse(ua);
+    assert_eq!(result.device().unwrap().kind, Some(DeviceType::Desktop));
+
+    let hints = ClientHints {
+        mobile: Some(true),
+        ..Default::default()
+    };
+    let result = dd.parse_with_hints(ua, Some(&hints));
+    assert_eq!(result.device().unwrap().kind, Some(DeviceType::Smartphone));
+}
+
+#[test]
+fn brands_grouped_by_device_file() {
+    let brands = detector().brands_by_type();
+    let consoles = &brands[&DeviceType::Console];
+    assert!(consoles.contains(&"Nintendo"));
+    assert!(consoles.contains(&"Sony"));
+    assert!(brands[&DeviceType::Smartphone].contains(&"Google"));
+    assert!(!brands[&DeviceType::Smartphone].contains(&"Sony"));
+}
+
+#[test]
+fn known_os_names_lists_static_names() {
+    let names = detector().known_os_names();
+    for name in ["Android", "iOS", "Windows"] {
+        assert!(names.contains(&name), "{}", name);
+    }
+    assert!(names.iter().all(|n| !n.contains('$')));
+
+    // The BSD entry's name is a capture group, so "FreeBSD" is not listed
+    // even though it can be detected.
+    let ua = "Mozilla/5.0 (X11; FreeBSD amd64; rv:120.0) Gecko/20100101 Firefox/120.0";
+    assert_eq!(detector().parse(ua).os().unwrap().name, "FreeBSD");
+    assert!(!names.contains(&"FreeBSD"));
+}
+
+const WINDOWS_CHROME: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+#[test]
+fn unknown_platform_hint_keeps_ua_os() {
+    let dd = detector();
+    for platform in ["\"Unknown\"", ""] {
+        let hints = ClientHints {
+            platform: Some(platform.to_string()),
+            ..Default::default()
+        };
+        let result = dd.parse_with_hints(WINDOWS_CHROME, Some(&hints));
+        let os = result.os().expect("expected os");
+        assert_eq!(os.name, "Windows");
+        assert_eq!(os.version, "10");
+    }
+}
+
+#[test]
+fn platform_hint_overrides_ua_os() {
+    let dd = detector();
+    let hints = ClientHints {
+        platform: Some("\"Linux\"".to_string()),
+        ..Default::default()
+    };
+    let result = dd.parse_with_hints(WINDOWS_CHROME, Some(&hints));
+    assert_eq!(result.os().expect("expected os").name, "GNU/Linux");
+
+    let hints = ClientHints {
+        platform: Some("\"Windows\"".to_string()),
+        platform_version: Some("\"15.0.0\"".to_string()),
+        ..Default::default()
+    };
+    let result = dd.parse_with_hints(WINDOWS_CHROME, Some(&hints));
+    let os = result.os().expect("expected os");
+    assert_eq!(os.name, "Windows");
+    assert_eq!(os.version, "11");
+}
+
+#[test]
+fn kindle_silk_is_amazon_tablet() {
+    let dd = detector();
+    let ua = "Mozilla/5.0 (Linux; U; en-us; KFTHWI Build/JDQ39) AppleWebKit/535.19 (KHTML, like Gecko) Silk/3.13 Safari/535.19 Silk-Accelerated=true";
+
+    let result = dd.parse(ua);
+    assert_eq!(
+        result.client().expect("expected client").name,
+        "Mobile Silk"
+    );
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Amazon");
+    assert_eq!(device.kind, Some(DeviceType::Tablet));
+}
+
+#[test]
+fn silk_on_fire_tablet_vs_fire_tv() {
+    let dd = detector();
+    for (ua, kind, model) in [
+        (
+            "Mozilla/5.0 (Linux; Android 9; KFMAWI Build/PS7326.3183N) AppleWebKit/537.36 (KHTML, like Gecko) Silk/120.3.1 like Chrome/120.0.6099.230 Safari/537.36",
+            DeviceType::Tablet,
+            "Fire HD 10 (2019)",
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 7.1.2; AFTMM) AppleWebKit/537.36 (KHTML, like Gecko) Silk/86.3.20 like Chrome/86.0.4240.198 Safari/537.36",
+            DeviceType::Tv,
+            "Fire TV Stick 4K",
+        ),
+        // An AFT code with no model entry only matches the brand gate.
+        (
+            "Mozilla/5.0 (Linux; Android 11; AFTXYZ) AppleWebKit/537.36 (KHTML, like Gecko) Silk/120.3.1 like Chrome/120.0.6099.230 Safari/537.36",
+            DeviceType::Tv,
+            "",
+        ),
+    ] {
+        let result = dd.parse(ua);
+        assert_eq!(result.client().expect("expected client").name, "Mobile Silk");
+        let device = result.device().expect("expected device");
+        assert_eq!(device.brand, "Amazon", "{}", ua);
+        assert_eq!(device.kind, Some(kind), "{}", ua);
+        assert_eq!(device.model, model);
+    }
+}
+
+#[test]
+fn e_readers_are_tablets() {
+    let dd = detector();
+
+    let kindle = "Mozilla/5.0 (X11; U; Linux armv7l like Android; en-us) AppleWebKit/531.2+ (KHTML, like Gecko) Version/5.0 Safari/531.2+ Kindle/3.0+";
+    let result = dd.parse(kindle);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Amazon");
+    assert_eq!(device.model, "Kindle");
+    assert_eq!(device.kind, Some(DeviceType::Tablet));
+
+    let kobo = "Mozilla/5.0 (Linux; U; Android 2.0; en-us;) AppleWebKit/533.1 (KHTML, like Gecko) Version/4.0 Mobile Safari/533.1 (Kobo Touch)";
+    let result = dd.parse(kobo);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.brand, "Rakuten Kobo");
+    assert_eq!(device.kind, Some(DeviceType::Tablet));
+
+    // Kobo's desktop reading app is not an e-reader.
+    let kobo_desktop = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Kobo Desktop Edition/4.36 Safari/537.36";
+    let result = dd.parse(kobo_desktop);
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Desktop));
+    assert_ne!(device.brand, "Rakuten Kobo");
+    assert_eq!(dd.detect_brand(kobo_desktop), None);
+    assert_eq!(dd.detect_brand(kobo), Some("Rakuten Kobo"));
+}
+
+#[test]
+fn all_client_matches_in_priority_order() {
+    let ua = "Mozilla/5.0 (X11; Linux x86_64) Chrome/120.0.0.0 Safari/537.36 curl/8.4.0";
+    let clients = detector().all_client_matches(ua);
+    let found: Vec<(ClientType, &str)> =
+        clients.iter().map(|c| (c.kind, c.name.as_ref())).collect();
+    assert_eq!(
+        found,
+        [
+            (ClientType::Browser, "Chrome"),
+            (ClientType::Library, "curl")
+        ]
+    );
+}
+
+#[test]
+fn mobile_safari_vs_safari() {
+    let dd = detector();
+
+    let iphone = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1";
+    let result = dd.parse(iphone);
+    let client = result.client().expect("expected client");
+    assert_eq!(client.name, "Mobile Safari");
+    assert_eq!(client.version, "17.1");
+
+    let result = dd.parse(MAC_SAFARI);
+    let client = result.client().expect("expected client");
+    assert_eq!(client.name, "Safari");
+    assert_eq!(client.version, "17.1");
+}
+
+#[test]
+fn standard_regex_only_drops_fancy_entries() {
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .standard_regex_only(true)
+        .build()
+        .unwrap();
+
+    let default_stats = detector().stats();
+    let stats = dd.stats();
+    assert!(default_stats.fancy_entries > 0);
+    assert_eq!(default_stats.dropped_entries, 0);
+    assert_eq!(stats.fancy_entries, 0);
+    assert_eq!(stats.dropped_entries, default_stats.fancy_entries);
+    assert_eq!(stats.standard_entries, default_stats.standard_entries);
+
+    // Common browsers use plain patterns and are still detected.
+    let result = dd.parse(WINDOWS_CHROME);
+    assert_eq!(result.client().expect("expected client").name, "Chrome");
+    assert_eq!(result.os().expect("expected os").name, "Windows");
+}
+
+fn hand_built(
+    os_version: &'static str,
+    browser_version: &'static str,
+    model: &'static str,
+) -> Detection<'static> {
+    Detection {
+        bot: None,
+        os: Some(Os {
+            name: Cow::Borrowed("Android"),
+            version: Cow::Borrowed(os_version),
+            platform: Cow::Borrowed(""),
+            version_inferred: false,
+        }),
+        client: Some(Client {
+            kind: ClientType::Browser,
+            name: Cow::Borrowed("Chrome Mobile"),
+            version: Cow::Borrowed(browser_version),
+            engine: Cow::Borrowed("Blink"),
+            engine_version: Cow::Borrowed(browser_version),
+            mode: None,
+            channel: None,
+        }),
+        device: Some(Device {
+            kind: Some(DeviceType::Smartphone),
+            brand: Cow::Borrowed("Google"),
+            model: Cow::Borrowed(model),
+        }),
+        android_webview: false,
+        emulator: false,
+        rule_ids: RuleIds::default(),
+    }
+}
+
+#[test]
+fn detection_diff_reports_changed_fields() {
+    let old = hand_built("13", "119.0", "Pixel 7");
+    let new = hand_built("14", "119.0", "Pixel 8");
+
+    let diff = DetectionDiff::compare(&old, &new);
+    assert_eq!(
+        diff.fields().collect::<Vec<_>>(),
+        [DetectionField::OsVersion, DetectionField::DeviceModel]
+    );
+    assert_eq!(
+        diff.to_string(),
+        "os.version: \"13\" -> \"14\"\ndevice.model: \"Pixel 7\" -> \"Pixel 8\""
+    );
+
+    assert!(DetectionDiff::compare(&old, &old).is_empty());
+
+    let mut no_device = hand_built("13", "119.0", "Pixel 7");
+    no_device.device = None;
+    let diff = DetectionDiff::compare(&old, &no_device);
+    assert_eq!(
+        diff.fields().collect::<Vec<_>>(),
+        [
+            DetectionField::DeviceType,
+            DetectionField::DeviceBrand,
+            DetectionField::DeviceModel
+        ]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Post hooks
+// ---------------------------------------------------------------------------
+
+#[test]
+fn post_hook_overrides_owned_detection() {
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .with_post_hook(Box::new(|ua, detection| {
+            if ua.contains("QA-Build") {
+                if let Some(device) = &mut detection.device {
+                    device.brand = "Internal".to_string();
+                }
+            }
+        }))
+        .build()
+        .unwrap();
+
+    let ua = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36 QA-Build";
+    let owned = dd.parse_owned(ua);
+    let device = owned.device.expect("expected device");
+    assert_eq!(device.brand, "Internal");
+    assert_eq!(device.model, "Pixel 8");
+    assert_eq!(owned.client.expect("expected client").name, "Chrome Mobile");
+
+    // The borrowed path is untouched.
+    let result = dd.parse(ua);
+    assert_eq!(result.device().expect("expected device").brand, "Google");
+
+    // Without a hook the owned result mirrors the borrowed one.
+    let result = detector().parse(ua);
+    let device = result.device().expect("expected device");
+    assert_eq!(
+        detector().parse_owned(ua).device.unwrap().brand,
+        device.brand
+    );
+}
+
+/// Names the in-house OS of a kiosk fleet that the dataset doesn't know.
+struct KioskOs;
+
+impl DetectionStage for KioskOs {
+    fn after(&self) -> PipelineStage {
+        PipelineStage::Os
+    }
+
+    fn run<'a>(&self, ua: &'a str, _hints: Option<&ClientHints>, detection: &mut Detection<'a>) {
+        if ua.contains("KioskOS/") {
+            detection.os = Some(Os {
+                name: Cow::Borrowed("GNU/Linux"),
+                version: Cow::Borrowed(""),
+                platform: Cow::Borrowed(""),
+                version_inferred: false,
+            });
+        }
+    }
+}
+
+#[test]
+fn version_formatter_pads_to_three_components() {
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .with_version_formatter(Box::new(|version| {
+            let mut parts: Vec<&str> = version.split('.').take(3).collect();
+            parts.resize(3, "0");
+            parts.join(".")
+        }))
+        .build()
+        .unwrap();
+
+    let result = dd.parse(WINDOWS_CHROME);
+    assert_eq!(result.os().unwrap().version, "10.0.0");
+    let client = result.client().unwrap();
+    assert_eq!(client.version, "120.0.0");
+
+    let result = dd.parse(MAC_SAFARI);
+    assert_eq!(result.client().unwrap().version, "17.1.0");
+    assert_eq!(dd.parse_owned(MAC_SAFARI).client.unwrap().version, "17.1.0");
+
+    assert_eq!(client.engine_version, "120.0.0");
+
+    // Empty versions stay empty.
+    let ua = "Mozilla/5.0 (X11; Linux x86_64; rv:120.0) Gecko/20100101 Firefox/120.0";
+    let result = dd.parse(ua);
+    let os = result.os().unwrap();
+    assert_eq!(os.name, "GNU/Linux");
+    assert_eq!(os.version, "");
+}
+
+#[test]
+fn custom_stage_runs_before_device_heuristics() {
+    let dd = DeviceDetector::builder(DATA_DIR)
+        .with_stage(Box::new(KioskOs))
+        .build()
+        .unwrap();
+
+    let ua = "Mozilla/5.0 (KioskOS/4.2) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+    assert!(detector().parse(ua).os().is_none());
+
+    // Desktop-OS inference saw the stage's OS, so the stage ran first.
+    let result = dd.parse(ua);
+    assert_eq!(result.os().expect("expected os").name, "GNU/Linux");
+    assert_eq!(result.client().expect("expected client").name, "Chrome");
+    let device = result.device().expect("expected device");
+    assert_eq!(device.kind, Some(DeviceType::Desktop));
+
+    assert_eq!(
+        dd.parse(WINDOWS_CHROME).into_owned(),
+        detector().parse(WINDOWS_CHROME).into_owned()
+    );
+}
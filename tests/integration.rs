@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use device_detector_rs::{ClientHints, DeviceDetector};
+use device_detector_rs::{ClientHints, Detection, DeviceDetector};
 use fixtures::fixtures;
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -73,6 +73,16 @@ struct BotFixtureProducer {
     url: Option<String>,
 }
 
+/// UA tokens of fetchers and link-preview agents whose fixture entries must
+/// also match on category, so they don't pass for crawlers or search bots.
+const PINNED_BOT_TOKENS: &[&str] = &[
+    "Feedfetcher-Google",
+    "Google-Read-Aloud",
+    "GoogleOther",
+    "facebookexternalhit",
+    "Slackbot",
+];
+
 #[fixtures(["vendor/device-detector/Tests/fixtures/bots.yml"])]
 #[test]
 fn test_bot_fixtures(path: &std::path::Path) {
@@ -90,6 +100,39 @@ fn test_bot_fixtures(path: &std::path::Path) {
             "bot name mismatch for UA: {}",
             f.user_agent
         );
+        if PINNED_BOT_TOKENS.iter().any(|t| f.user_agent.contains(t)) {
+            assert_eq!(
+                bot.category,
+                f.bot.category.as_deref(),
+                "bot category mismatch for UA: {}",
+                f.user_agent
+            );
+        }
+    }
+}
+
+#[fixtures(["vendor/device-detector/Tests/fixtures/bots.yml"])]
+#[test]
+fn test_bot_fixtures_regex_set_gate(path: &std::path::Path) {
+    static GATED: OnceLock<DeviceDetector> = OnceLock::new();
+    let gated = GATED.get_or_init(|| {
+        DeviceDetector::builder("vendor/device-detector/regexes")
+            .regex_set_gate(true)
+            .build()
+            .expect("failed to build DeviceDetector")
+    });
+    let dd = make_detector();
+    let content = std::fs::read_to_string(path).unwrap();
+    let fixtures: Vec<BotFixture> = serde_yaml::from_str(&content).unwrap();
+
+    for f in &fixtures {
+        let expected = dd.parse(&f.user_agent).bot().map(|b| b.name.to_string());
+        let actual = gated.parse(&f.user_agent).bot().map(|b| b.name.to_string());
+        assert_eq!(
+            actual, expected,
+            "gate changed bot for UA: {}",
+            f.user_agent
+        );
     }
 }
 
@@ -128,6 +171,8 @@ struct ClientFixture {
     name: Option<String>,
     #[serde(default)]
     version: Option<String>,
+    #[serde(default)]
+    engine: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -140,6 +185,100 @@ struct DeviceFixtureData {
     model: Option<String>,
 }
 
+/// Client names whose fixture entries must also match on client name, type
+/// and engine, and on OS name, not just device brand.
+const PINNED_CLIENTS: &[&str] = &[
+    "Opera GX",
+    "Opera Crypto",
+    "Microsoft Edge",
+    "Chromium",
+    "Internet Explorer",
+    "Overcast",
+    "Pocket Casts",
+    "Apple Podcasts",
+];
+
+/// Device models whose fixture entries must also match on device type and
+/// model.
+const PINNED_MODELS: &[&str] = &["PlayStation 5", "Switch", "Xbox Series X"];
+
+/// OS names whose fixture entries must also match on OS name.
+const PINNED_OSES: &[&str] = &["Ubuntu", "Fedora", "Manjaro", "Nintendo", "PlayStation"];
+
+/// Checks beyond the device brand for fixture entries that expect a pinned
+/// name.
+fn check_pinned(f: &DeviceFixture, result: &Detection<'_>) {
+    let expected_client = f.client.as_ref();
+    if let Some(name) = expected_client.and_then(|c| c.name.as_deref()) {
+        if PINNED_CLIENTS.contains(&name) {
+            let client = result
+                .client()
+                .unwrap_or_else(|| panic!("expected client {:?} for UA: {}", name, f.user_agent));
+            assert_eq!(
+                client.name, name,
+                "client name mismatch for UA: {}",
+                f.user_agent
+            );
+            if let Some(kind) = expected_client.and_then(|c| c.kind.as_deref()) {
+                assert_eq!(
+                    client.kind.as_str(),
+                    kind,
+                    "client type mismatch for UA: {}",
+                    f.user_agent
+                );
+            }
+            if let Some(engine) = expected_client.and_then(|c| c.engine.as_deref()) {
+                assert_eq!(
+                    client.engine, engine,
+                    "client engine mismatch for UA: {}",
+                    f.user_agent
+                );
+            }
+            if let Some(os) = f.os.as_ref().and_then(|o| o.name.as_deref()) {
+                assert_eq!(
+                    result.os().map_or("", |o| o.name.as_ref()),
+                    os,
+                    "os name mismatch for UA: {}",
+                    f.user_agent
+                );
+            }
+        }
+    }
+
+    if let Some(os) = f.os.as_ref().and_then(|o| o.name.as_deref()) {
+        if PINNED_OSES.contains(&os) {
+            assert_eq!(
+                result.os().map_or("", |o| o.name.as_ref()),
+                os,
+                "os name mismatch for UA: {}",
+                f.user_agent
+            );
+        }
+    }
+
+    let expected_device = f.device.as_ref();
+    if let Some(model) = expected_device.and_then(|d| d.model.as_deref()) {
+        if PINNED_MODELS.contains(&model) {
+            let device = result
+                .device()
+                .unwrap_or_else(|| panic!("expected device {:?} for UA: {}", model, f.user_agent));
+            assert_eq!(
+                device.model, model,
+                "device model mismatch for UA: {}",
+                f.user_agent
+            );
+            if let Some(kind) = expected_device.and_then(|d| d.kind.as_deref()) {
+                assert_eq!(
+                    device.kind.map_or("", |k| k.as_str()),
+                    kind,
+                    "device type mismatch for UA: {}",
+                    f.user_agent
+                );
+            }
+        }
+    }
+}
+
 #[fixtures([
     "vendor/device-detector/Tests/fixtures/camera*.yml",
     "vendor/device-detector/Tests/fixtures/car_browser*.yml",
@@ -174,6 +313,7 @@ fn test_device_fixtures(path: &std::path::Path) {
         }
 
         let result = dd.parse(&f.user_agent);
+        check_pinned(f, &result);
 
         if let Some(expected_device) = &f.device {
             if let Some(expected_brand) = &expected_device.brand {
@@ -262,6 +402,25 @@ fn build_hints(headers: &Option<HashMap<String, serde_yaml::Value>>) -> ClientHi
         }
     }
 
+    // Sec-CH-UA-Platform / Sec-CH-UA-Platform-Version
+    if let Some(s) = headers.get("Sec-CH-UA-Platform").and_then(|v| v.as_str()) {
+        hints.platform = Some(s.trim_matches('"').to_string());
+    }
+    if let Some(s) = headers
+        .get("Sec-CH-UA-Platform-Version")
+        .and_then(|v| v.as_str())
+    {
+        hints.platform_version = Some(s.trim_matches('"').to_string());
+    }
+
+    // Sec-CH-UA-Arch / Sec-CH-UA-Bitness
+    if let Some(s) = headers.get("Sec-CH-UA-Arch").and_then(|v| v.as_str()) {
+        hints.architecture = Some(s.trim_matches('"').to_string());
+    }
+    if let Some(s) = headers.get("Sec-CH-UA-Bitness").and_then(|v| v.as_str()) {
+        hints.bitness = Some(s.trim_matches('"').to_string());
+    }
+
     hints
 }
 